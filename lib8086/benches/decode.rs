@@ -0,0 +1,71 @@
+//! Decode throughput micro-benchmark.
+//!
+//! Repeatedly decodes the `draw_rectangle` test program, once with the OPCODE
+//! lookup tables and once with the string-matching parser they replaced, and
+//! reports how many instructions and bytes per second each gets through. Run it
+//! with `cargo bench -p lib8086 --bench decode --features legacy-opcodes`,
+//! optionally passing the number of iterations as the first argument.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use lib8086::code::{DResult, Instruction};
+use lib8086::Decoder;
+
+const PROGRAM: &[u8] = include_bytes!("../../sim8086/tests/data/execute/draw_rectangle");
+const DEFAULT_ITERATIONS: usize = 200_000;
+
+/// Decodes the whole program once, returning the number of instructions.
+fn decode_program(bytes: &[u8], decode: fn(&[u8]) -> DResult<Instruction>) -> usize {
+    let mut remaining = bytes;
+    let mut count = 0;
+    while !remaining.is_empty() {
+        let (instruction, size) = decode(remaining).expect("The benchmark program should decode.");
+        black_box(instruction);
+        remaining = &remaining[size..];
+        count += 1;
+    }
+    count
+}
+
+/// Decodes the program `iterations` times and prints the throughput, returning
+/// the number of instructions decoded per second.
+fn bench(name: &str, iterations: usize, decode: fn(&[u8]) -> DResult<Instruction>) -> f64 {
+    // Warm up caches and the branch predictor before timing.
+    for _ in 0..iterations / 10 {
+        decode_program(black_box(PROGRAM), decode);
+    }
+
+    let start = Instant::now();
+    let mut instructions = 0;
+    for _ in 0..iterations {
+        instructions += decode_program(black_box(PROGRAM), decode);
+    }
+    let elapsed = start.elapsed();
+
+    let secs = elapsed.as_secs_f64();
+    let bytes = PROGRAM.len() * iterations;
+    println!("{name}: decoded draw_rectangle {iterations} times in {elapsed:.2?}");
+    println!("  {:>12.0} instructions/s", instructions as f64 / secs);
+    println!("  {:>12.2} MB/s", bytes as f64 / secs / 1e6);
+    println!(
+        "  {:>12.1} ns/instruction",
+        elapsed.as_nanos() as f64 / instructions as f64
+    );
+    instructions as f64 / secs
+}
+
+fn main() {
+    let iterations = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(DEFAULT_ITERATIONS);
+
+    let before = bench(
+        "string patterns",
+        iterations,
+        Decoder::try_decode_next_legacy,
+    );
+    let after = bench("lookup tables", iterations, Decoder::try_decode_next);
+    println!("lookup tables are {:.1}x faster", after / before);
+}
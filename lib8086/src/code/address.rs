@@ -1,6 +1,6 @@
 use std::fmt;
 
-use super::{DResult, DecodeError, Mode, Register};
+use super::{byte_at, word_at, DResult, DecodeError, Field, Mode, Register, SegmentRegister};
 
 #[derive(Debug, PartialEq)]
 pub enum Displacement {
@@ -20,18 +20,18 @@ impl Displacement {
         match mode {
             Mode::Memory => {
                 if rm == 0b110 {
-                    let addr = u16::from_le_bytes([bytes[0], bytes[1]]);
+                    let addr = u16::from_le_bytes(word_at(bytes, 0)?);
                     Ok((Displacement::NoneDirect(addr), 2))
                 } else {
                     Ok((Displacement::None, 0))
                 }
             }
-            Mode::Memory8 => Ok((Displacement::Byte(bytes[0] as i8), 1)),
+            Mode::Memory8 => Ok((Displacement::Byte(byte_at(bytes, 0)? as i8), 1)),
             Mode::Memory16 => {
-                let addr = i16::from_le_bytes([bytes[0], bytes[1]]);
+                let addr = i16::from_le_bytes(word_at(bytes, 0)?);
                 Ok((Displacement::Word(addr), 2))
             }
-            Mode::Register => Err(DecodeError::Displacement),
+            Mode::Register => Err(DecodeError::invalid_field(Field::Mod, 0b11)),
         }
     }
 
@@ -54,12 +54,11 @@ impl fmt::Display for Displacement {
             Self::NoneDirect(v) => write!(f, " + {v}"),
             Self::Word(v) => {
                 let sign = if *v < 0 { '-' } else { '+' };
-                write!(f, " {sign} {}", v.abs())
+                write!(f, " {sign} {}", v.unsigned_abs())
             }
             Self::Byte(v) => {
-                let v = (*v as i16).abs();
-                let sign = if v < 0 { '-' } else { '+' };
-                write!(f, " {sign} {v}")
+                let sign = if *v < 0 { '-' } else { '+' };
+                write!(f, " {sign} {}", v.unsigned_abs())
             }
             Self::None => write!(f, ""),
         }
@@ -131,6 +130,29 @@ impl EffectiveAddr {
     }
 }
 
+impl EffectiveAddr {
+    /// Returns the segment the effective address refers to when there is no
+    /// segment override prefix.
+    ///
+    /// Addresses based on the `BP` register refer to the stack segment; all others
+    /// refer to the data segment.
+    pub fn default_segment(&self) -> SegmentRegister {
+        match self {
+            Self::Reg(Register::BP)
+            | Self::RegDisp {
+                base: Register::BP, ..
+            }
+            | Self::RegPair {
+                base: Register::BP, ..
+            }
+            | Self::RegPairDisp {
+                base: Register::BP, ..
+            } => SegmentRegister::SS,
+            _ => SegmentRegister::DS,
+        }
+    }
+}
+
 impl fmt::Display for EffectiveAddr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -143,6 +165,42 @@ impl fmt::Display for EffectiveAddr {
     }
 }
 
+/// A memory operand, made up of an effective address and an optional segment
+/// override.
+///
+/// Without an override, the address refers to its default segment.
+#[derive(Debug, PartialEq)]
+pub struct MemoryAddr {
+    pub addr: EffectiveAddr,
+    pub segment: Option<SegmentRegister>,
+}
+
+impl MemoryAddr {
+    /// Creates a new memory operand in the default segment of the effective address.
+    #[inline]
+    pub fn new(addr: EffectiveAddr) -> Self {
+        Self {
+            addr,
+            segment: None,
+        }
+    }
+
+    /// Returns the segment the memory operand refers to.
+    #[inline]
+    pub fn segment(&self) -> SegmentRegister {
+        self.segment.unwrap_or(self.addr.default_segment())
+    }
+}
+
+impl fmt::Display for MemoryAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.segment {
+            Some(segment) => write!(f, "{segment}:{}", self.addr),
+            None => write!(f, "{}", self.addr),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -210,4 +268,30 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_segments() {
+        let addr = EffectiveAddr::new(0b010, Displacement::None);
+        assert_eq!(addr.default_segment(), SegmentRegister::SS);
+
+        let addr = EffectiveAddr::new(0b111, Displacement::Byte(4));
+        assert_eq!(addr.default_segment(), SegmentRegister::DS);
+
+        let mut mem = MemoryAddr::new(EffectiveAddr::new(0b000, Displacement::None));
+        assert_eq!(mem.segment(), SegmentRegister::DS);
+        assert_eq!(mem.to_string(), "[bx + si]");
+
+        mem.segment = Some(SegmentRegister::ES);
+        assert_eq!(mem.segment(), SegmentRegister::ES);
+        assert_eq!(mem.to_string(), "es:[bx + si]");
+    }
+
+    #[test]
+    fn test_display_displacement_edges() {
+        assert_eq!(Displacement::Byte(i8::MIN).to_string(), " - 128");
+        assert_eq!(Displacement::Byte(-1).to_string(), " - 1");
+        assert_eq!(Displacement::Byte(i8::MAX).to_string(), " + 127");
+        assert_eq!(Displacement::Word(i16::MIN).to_string(), " - 32768");
+        assert_eq!(Displacement::Word(i16::MAX).to_string(), " + 32767");
+    }
 }
@@ -1,5 +1,19 @@
 use super::ops::*;
-use super::{DResult, DecodeError, Instruction, InstructionQueue};
+use super::{
+    byte_at, DResult, DecodeError, DecodeErrorKind, Field, Instruction, InstructionQueue,
+    SegmentRegister, SR,
+};
+
+/// A function that extracts the OPCODE from the first byte of an instruction.
+trait ParseOpCode: Fn(u8) -> Result<OpCode, DecodeError> + Copy {}
+
+impl<F: Fn(u8) -> Result<OpCode, DecodeError> + Copy> ParseOpCode for F {}
+
+/// The maximum number of prefixes of an instruction.
+///
+/// The 8086 accepts any number of them, but later x86 processors limit an
+/// instruction to 15 bytes, which leaves room for at most 14 prefixes.
+const MAX_PREFIXES: usize = 14;
 
 pub struct Decoder {
     buffer: Vec<u8>,
@@ -10,11 +24,16 @@ impl Decoder {
         Self { buffer }
     }
 
+    /// Decodes the buffer, failing on the first instruction that cannot be decoded.
+    ///
+    /// The offset of the returned error is the offset of the instruction in the buffer.
     pub fn decode(self) -> Result<InstructionQueue, DecodeError> {
         let mut iqueue = InstructionQueue::default();
         let mut remaining_bytes = self.buffer.as_slice();
         while !remaining_bytes.is_empty() {
-            let (instr, size) = Decoder::try_decode_next(remaining_bytes)?;
+            let offset = self.buffer.len() - remaining_bytes.len();
+            let (instr, size) = Decoder::try_decode_next(remaining_bytes)
+                .map_err(|err| DecodeError { offset, ..err })?;
             iqueue.push(instr, size);
             remaining_bytes = &remaining_bytes[size..];
         }
@@ -23,15 +42,140 @@ impl Decoder {
         Ok(iqueue)
     }
 
+    /// Decodes the buffer, emitting the bytes that cannot be decoded as data.
+    ///
+    /// Binaries often mix code with data tables. Instead of failing on the first
+    /// byte that does not start a valid instruction, the byte is emitted as
+    /// `Instruction::Data` and decoding resumes at the next byte. The number of
+    /// undecoded bytes is available through `InstructionQueue::undecoded`.
+    pub fn decode_lenient(self) -> InstructionQueue {
+        let mut iqueue = InstructionQueue::default();
+        let mut remaining_bytes = self.buffer.as_slice();
+        while !remaining_bytes.is_empty() {
+            let (instr, size) = Decoder::try_decode_next(remaining_bytes)
+                .unwrap_or((Instruction::Data(remaining_bytes[0]), 1));
+            iqueue.push(instr, size);
+            remaining_bytes = &remaining_bytes[size..];
+        }
+
+        iqueue.push(Instruction::Halt, 0);
+        iqueue
+    }
+
     /// Tries to decode next instruction from the binary representation of the machine code.
     ///
     /// Takes a reference to an array of bytes that represent the machine code, and attempts
     /// to decode the next instruction. If it succeeds, it returns the instruction and a
     /// reference to the array with the rest of the bytes. Otherwise, it returns the decoding.
     /// error.
+    ///
+    /// Decoding never panics: if the bytes end in the middle of an instruction, an
+    /// `UnexpectedEof` error is returned. The error holds the bytes of the instruction
+    /// involved, and its offset is zero, since the position of the bytes in the
+    /// byte-code is not known.
     pub fn try_decode_next(bytes: &[u8]) -> DResult<Instruction> {
+        Decoder::try_decode_next_with(bytes, OpCode::parse)
+    }
+
+    /// Tries to decode next instruction like `try_decode_next`, but extracts the
+    /// OPCODE with `OpCode::parse_pattern`, the string matcher that the lookup
+    /// tables replaced.
+    ///
+    /// It is only meant to compare the decode throughput of both.
+    #[cfg(feature = "legacy-opcodes")]
+    pub fn try_decode_next_legacy(bytes: &[u8]) -> DResult<Instruction> {
+        Decoder::try_decode_next_with(bytes, OpCode::parse_pattern)
+    }
+
+    /// Tries to decode next instruction, extracting the OPCODE from the first byte
+    /// with `parse`.
+    fn try_decode_next_with(bytes: &[u8], parse: impl ParseOpCode) -> DResult<Instruction> {
+        Decoder::try_decode_instruction(bytes, parse).map_err(|mut err| {
+            if err.bytes.is_empty() {
+                // Fields other than the OPCODE are all in the ModRM byte that follows it.
+                let len = match err.kind {
+                    DecodeErrorKind::UnexpectedEof { .. } => bytes.len(),
+                    DecodeErrorKind::InvalidField {
+                        field: Field::OpCode,
+                        ..
+                    } => 1,
+                    _ => 2,
+                };
+                err.bytes = bytes[..len.min(bytes.len())].to_vec();
+            }
+            err
+        })
+    }
+
+    /// Tries to decode an instruction that starts with prefix bytes: LOCK, REP or
+    /// segment overrides.
+    ///
+    /// The prefixes are decoded together with the instruction that follows them,
+    /// as a single instruction. The prefix nearest to the instruction applies
+    /// first. More than `MAX_PREFIXES` prefixes are an invalid encoding.
+    fn try_decode_prefixed(bytes: &[u8], parse: impl ParseOpCode) -> DResult<Instruction> {
+        use crate::code::ops::OpCode::*;
+        let count = bytes
+            .iter()
+            .take_while(|&&byte| {
+                matches!(
+                    parse(byte),
+                    Ok(Lock | Rep | SegOverrideES | SegOverrideCS | SegOverrideSS | SegOverrideDS)
+                )
+            })
+            .take(MAX_PREFIXES + 1)
+            .count();
+        if count > MAX_PREFIXES {
+            return Err(DecodeError {
+                bytes: bytes[..count].to_vec(),
+                ..DecodeError::invalid_encoding("too many prefixes precede the instruction")
+            });
+        }
+
+        let (mut instruction, mut size) = Decoder::try_decode_next_with(&bytes[count..], parse)
+            .map_err(|err| {
+                bytes[..count]
+                    .iter()
+                    .rev()
+                    .fold(err, |err, &prefix| err.prefixed(prefix))
+            })?;
+        for idx in (0..count).rev() {
+            size += 1;
+            instruction = match parse(bytes[idx])? {
+                Lock => Instruction::Lock(Box::new(instruction)),
+                Rep => match instruction {
+                    Instruction::String(op) => {
+                        Instruction::String(op.with_rep(RepPrefix::parse_byte(bytes[idx])))
+                    }
+                    _ => {
+                        return Err(DecodeError {
+                            bytes: bytes[..idx + size].to_vec(),
+                            ..DecodeError::invalid_encoding("REP must precede a string instruction")
+                        })
+                    }
+                },
+                SegOverrideES | SegOverrideCS | SegOverrideSS | SegOverrideDS => {
+                    let sr = SR::parse_byte(bytes[idx]);
+                    let segment = SegmentRegister::try_from(sr.as_u8())?;
+                    if !instruction.set_segment(segment) {
+                        return Err(DecodeError {
+                            bytes: bytes[..idx + size].to_vec(),
+                            ..DecodeError::invalid_encoding(
+                                "segment override precedes an instruction without memory operands",
+                            )
+                        });
+                    }
+                    instruction
+                }
+                _ => unreachable!("only prefixes are counted"),
+            };
+        }
+        Ok((instruction, size))
+    }
+
+    fn try_decode_instruction(bytes: &[u8], parse: impl ParseOpCode) -> DResult<Instruction> {
         use crate::code::ops::OpCode::*;
-        match OpCode::parse(bytes[0])? {
+        match parse(byte_at(bytes, 0)?)? {
             Halt => Ok((Instruction::Halt, 1)),
             MovRegRM => {
                 let (op, size) = MovOp::try_parse_reg_rm(bytes)?;
@@ -53,10 +197,32 @@ impl Decoder {
                 let (op, size) = MovOp::try_decode_rm_segreg(bytes)?;
                 Ok((Instruction::Mov(op), size))
             }
-            NumImRM => {
-                let (op, size) = NumOp::try_decode_im_rm(bytes)?;
-                Ok((Instruction::Num(op), size))
+            XchgRMReg => {
+                let (op, size) = TransferOp::try_decode_xchg_rm_reg(bytes)?;
+                Ok((Instruction::Transfer(op), size))
+            }
+            XchgAcc => {
+                let (op, size) = TransferOp::try_decode_xchg_acc(bytes)?;
+                Ok((Instruction::Transfer(op), size))
+            }
+            Xlat => Ok((Instruction::Transfer(TransferOp::Xlat { segment: None }), 1)),
+            Lea => {
+                let (op, size) = TransferOp::try_decode_load_addr(bytes, LoadAddrOpType::Lea)?;
+                Ok((Instruction::Transfer(op), size))
             }
+            Lds => {
+                let (op, size) = TransferOp::try_decode_load_addr(bytes, LoadAddrOpType::Lds)?;
+                Ok((Instruction::Transfer(op), size))
+            }
+            Les => {
+                let (op, size) = TransferOp::try_decode_load_addr(bytes, LoadAddrOpType::Les)?;
+                Ok((Instruction::Transfer(op), size))
+            }
+            Lahf => Ok((Instruction::Transfer(TransferOp::Lahf), 1)),
+            Sahf => Ok((Instruction::Transfer(TransferOp::Sahf), 1)),
+            Pushf => Ok((Instruction::Transfer(TransferOp::Pushf), 1)),
+            Popf => Ok((Instruction::Transfer(TransferOp::Popf), 1)),
+            NumImRM | Group3 | Group4 | Group5 => Decoder::try_decode_group(bytes),
             AddRMReg => {
                 let (op, size) = NumOp::try_decode_rm_reg(bytes, NumOpType::Add)?;
                 Ok((Instruction::Num(op), size))
@@ -97,68 +263,200 @@ impl Decoder {
                 let (op, size) = NumOp::try_decode_im_acc(bytes, NumOpType::Cmp)?;
                 Ok((Instruction::Num(op), size))
             }
-            JumpEqual => Ok((Instruction::CondJump(CondJumpOp::Equal(bytes[1] as i8)), 2)),
-            JumpLess => Ok((Instruction::CondJump(CondJumpOp::Less(bytes[1] as i8)), 2)),
+            Daa | Das | Aaa | Aas | Aam | Aad => {
+                let (op, size) = AdjustOp::try_decode(bytes)?;
+                Ok((Instruction::Adjust(op), size))
+            }
+            AndRMReg => {
+                let (op, size) = LogicOp::try_decode_rm_reg(bytes, LogicOpType::And)?;
+                Ok((Instruction::Logic(op), size))
+            }
+            OrRMReg => {
+                let (op, size) = LogicOp::try_decode_rm_reg(bytes, LogicOpType::Or)?;
+                Ok((Instruction::Logic(op), size))
+            }
+            XorRMReg => {
+                let (op, size) = LogicOp::try_decode_rm_reg(bytes, LogicOpType::Xor)?;
+                Ok((Instruction::Logic(op), size))
+            }
+            TestRMReg => {
+                let (op, size) = LogicOp::try_decode_rm_reg(bytes, LogicOpType::Test)?;
+                Ok((Instruction::Logic(op), size))
+            }
+            AndImAcc => {
+                let (op, size) = LogicOp::try_decode_im_acc(bytes, LogicOpType::And)?;
+                Ok((Instruction::Logic(op), size))
+            }
+            OrImAcc => {
+                let (op, size) = LogicOp::try_decode_im_acc(bytes, LogicOpType::Or)?;
+                Ok((Instruction::Logic(op), size))
+            }
+            XorImAcc => {
+                let (op, size) = LogicOp::try_decode_im_acc(bytes, LogicOpType::Xor)?;
+                Ok((Instruction::Logic(op), size))
+            }
+            TestImAcc => {
+                let (op, size) = LogicOp::try_decode_im_acc(bytes, LogicOpType::Test)?;
+                Ok((Instruction::Logic(op), size))
+            }
+            ShiftRotate => {
+                let (op, size) = ShiftOp::try_decode(bytes)?;
+                Ok((Instruction::Shift(op), size))
+            }
+            JumpEqual => Ok((
+                Instruction::CondJump(CondJumpOp::Equal(byte_at(bytes, 1)? as i8)),
+                2,
+            )),
+            JumpLess => Ok((
+                Instruction::CondJump(CondJumpOp::Less(byte_at(bytes, 1)? as i8)),
+                2,
+            )),
             JumpLessEq => Ok((
-                Instruction::CondJump(CondJumpOp::LessEqual(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::LessEqual(byte_at(bytes, 1)? as i8)),
+                2,
+            )),
+            JumpBelow => Ok((
+                Instruction::CondJump(CondJumpOp::Below(byte_at(bytes, 1)? as i8)),
                 2,
             )),
-            JumpBelow => Ok((Instruction::CondJump(CondJumpOp::Below(bytes[1] as i8)), 2)),
             JumpBelowEq => Ok((
-                Instruction::CondJump(CondJumpOp::BelowEqual(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::BelowEqual(byte_at(bytes, 1)? as i8)),
                 2,
             )),
             JumpParityEven => Ok((
-                Instruction::CondJump(CondJumpOp::ParityEven(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::ParityEven(byte_at(bytes, 1)? as i8)),
                 2,
             )),
             JumpOverflow => Ok((
-                Instruction::CondJump(CondJumpOp::Overflow(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::Overflow(byte_at(bytes, 1)? as i8)),
                 2,
             )),
             JumpNEqual => Ok((
-                Instruction::CondJump(CondJumpOp::NotEqual(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::NotEqual(byte_at(bytes, 1)? as i8)),
+                2,
+            )),
+            JumpSign => Ok((
+                Instruction::CondJump(CondJumpOp::Sign(byte_at(bytes, 1)? as i8)),
                 2,
             )),
-            JumpSign => Ok((Instruction::CondJump(CondJumpOp::Sign(bytes[1] as i8)), 2)),
             JumpGreaterEq => Ok((
-                Instruction::CondJump(CondJumpOp::GreaterEqual(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::GreaterEqual(byte_at(bytes, 1)? as i8)),
                 2,
             )),
             JumpGreater => Ok((
-                Instruction::CondJump(CondJumpOp::Greater(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::Greater(byte_at(bytes, 1)? as i8)),
                 2,
             )),
             JumpAboveEq => Ok((
-                Instruction::CondJump(CondJumpOp::AboveEqual(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::AboveEqual(byte_at(bytes, 1)? as i8)),
+                2,
+            )),
+            JumpAbove => Ok((
+                Instruction::CondJump(CondJumpOp::Above(byte_at(bytes, 1)? as i8)),
                 2,
             )),
-            JumpAbove => Ok((Instruction::CondJump(CondJumpOp::Above(bytes[1] as i8)), 2)),
             JumpParityOdd => Ok((
-                Instruction::CondJump(CondJumpOp::ParityOdd(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::ParityOdd(byte_at(bytes, 1)? as i8)),
                 2,
             )),
             JumpNOverflow => Ok((
-                Instruction::CondJump(CondJumpOp::NotOverflow(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::NotOverflow(byte_at(bytes, 1)? as i8)),
                 2,
             )),
             JumpNSign => Ok((
-                Instruction::CondJump(CondJumpOp::NotSign(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::NotSign(byte_at(bytes, 1)? as i8)),
+                2,
+            )),
+            Loop => Ok((
+                Instruction::CondJump(CondJumpOp::Loop(byte_at(bytes, 1)? as i8)),
                 2,
             )),
-            Loop => Ok((Instruction::CondJump(CondJumpOp::Loop(bytes[1] as i8)), 2)),
             LoopEqual => Ok((
-                Instruction::CondJump(CondJumpOp::LoopEqual(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::LoopEqual(byte_at(bytes, 1)? as i8)),
                 2,
             )),
             LoopNEqual => Ok((
-                Instruction::CondJump(CondJumpOp::LoopNEqual(bytes[1] as i8)),
+                Instruction::CondJump(CondJumpOp::LoopNEqual(byte_at(bytes, 1)? as i8)),
                 2,
             )),
-            JumpCXZero => Ok((Instruction::CondJump(CondJumpOp::CXZero(bytes[1] as i8)), 2)),
-            PushRegRM => {
-                let (op, size) = PushOp::try_decode_rm(bytes)?;
-                Ok((Instruction::Push(op), size))
+            JumpCXZero => Ok((
+                Instruction::CondJump(CondJumpOp::CXZero(byte_at(bytes, 1)? as i8)),
+                2,
+            )),
+            JumpShort => {
+                let (op, size) = JumpOp::try_decode_direct(bytes, TargetType::Short)?;
+                Ok((Instruction::Jump(op), size))
+            }
+            JumpNear => {
+                let (op, size) = JumpOp::try_decode_direct(bytes, TargetType::Near)?;
+                Ok((Instruction::Jump(op), size))
+            }
+            JumpFar => {
+                let (op, size) = JumpOp::try_decode_direct(bytes, TargetType::Far)?;
+                Ok((Instruction::Jump(op), size))
+            }
+            CallNear => {
+                let (op, size) = CallOp::try_decode_direct(bytes, TargetType::Near)?;
+                Ok((Instruction::Call(op), size))
+            }
+            CallFar => {
+                let (op, size) = CallOp::try_decode_direct(bytes, TargetType::Far)?;
+                Ok((Instruction::Call(op), size))
+            }
+            Ret | RetFar => {
+                let (op, size) = RetOp::try_decode(bytes)?;
+                Ok((Instruction::Ret(op), size))
+            }
+            InIm | OutIm | InDX | OutDX => {
+                let (op, size) = IoOp::try_decode(bytes)?;
+                Ok((Instruction::Io(op), size))
+            }
+            Clc | Stc | Cmc | Cld | Std | Cli | Sti | Wait => {
+                let (op, size) = ControlOp::try_decode(bytes)?;
+                Ok((Instruction::Control(op), size))
+            }
+            Esc => {
+                let (op, size) = ControlOp::try_decode_esc(bytes)?;
+                Ok((Instruction::Control(op), size))
+            }
+            Cbw | Cwd => {
+                let (op, size) = ConvertOp::try_decode(bytes)?;
+                Ok((Instruction::Convert(op), size))
+            }
+            Int3 | Int | Into | Iret => {
+                let (op, size) = InterruptOp::try_decode(bytes)?;
+                Ok((Instruction::Interrupt(op), size))
+            }
+            Movs => {
+                let (op, size) = StringOp::try_decode(bytes, StringOpType::Movs)?;
+                Ok((Instruction::String(op), size))
+            }
+            Cmps => {
+                let (op, size) = StringOp::try_decode(bytes, StringOpType::Cmps)?;
+                Ok((Instruction::String(op), size))
+            }
+            Stos => {
+                let (op, size) = StringOp::try_decode(bytes, StringOpType::Stos)?;
+                Ok((Instruction::String(op), size))
+            }
+            Lods => {
+                let (op, size) = StringOp::try_decode(bytes, StringOpType::Lods)?;
+                Ok((Instruction::String(op), size))
+            }
+            Scas => {
+                let (op, size) = StringOp::try_decode(bytes, StringOpType::Scas)?;
+                Ok((Instruction::String(op), size))
+            }
+            Lock | Rep | SegOverrideES | SegOverrideCS | SegOverrideSS | SegOverrideDS => {
+                Decoder::try_decode_prefixed(bytes, parse)
+            }
+            IncReg => {
+                let (op, size) = NumOp::try_decode_reg(bytes, UnaryOpType::Inc)?;
+                Ok((Instruction::Num(op), size))
+            }
+            DecReg => {
+                let (op, size) = NumOp::try_decode_reg(bytes, UnaryOpType::Dec)?;
+                Ok((Instruction::Num(op), size))
             }
             PushReg => {
                 let (op, size) = PushOp::try_decode_reg(bytes)?;
@@ -185,8 +483,234 @@ impl Decoder {
                     assert_eq!(size, 1);
                     Ok((Instruction::Pop(op), size))
                 }
-                _ => Err(DecodeError::OpCode(format!("{}", bytes[0]))),
+                _ => Err(DecodeError::invalid_field(Field::OpCode, bytes[0])),
             },
         }
     }
+
+    /// Decodes an instruction of a group OPCODE, whose operation is selected by the
+    /// REG field of the ModRM byte.
+    fn try_decode_group(bytes: &[u8]) -> DResult<Instruction> {
+        use crate::code::ops::GroupOpCode::*;
+        let modrm = byte_at(bytes, 1)?;
+        match GroupOpCode::parse(bytes[0], modrm)? {
+            Add | Adc | Sub | Sbb | Cmp => {
+                let (op, size) = NumOp::try_decode_im_rm(bytes)?;
+                Ok((Instruction::Num(op), size))
+            }
+            And | Or | Xor => {
+                let optype = LogicOpType::try_parse_byte(modrm)?;
+                let (op, size) = LogicOp::try_decode_im_rm(bytes, optype)?;
+                Ok((Instruction::Logic(op), size))
+            }
+            Test => {
+                let (op, size) = LogicOp::try_decode_im_rm(bytes, LogicOpType::Test)?;
+                Ok((Instruction::Logic(op), size))
+            }
+            Not => {
+                let (op, size) = LogicOp::try_decode_not(bytes)?;
+                Ok((Instruction::Logic(op), size))
+            }
+            Neg => {
+                let (op, size) = NumOp::try_decode_rm(bytes, UnaryOpType::Neg)?;
+                Ok((Instruction::Num(op), size))
+            }
+            Mul | Imul | Div | Idiv => {
+                let (op, size) = MulDivOp::try_decode(bytes)?;
+                Ok((Instruction::MulDiv(op), size))
+            }
+            Inc => {
+                let (op, size) = NumOp::try_decode_rm(bytes, UnaryOpType::Inc)?;
+                Ok((Instruction::Num(op), size))
+            }
+            Dec => {
+                let (op, size) = NumOp::try_decode_rm(bytes, UnaryOpType::Dec)?;
+                Ok((Instruction::Num(op), size))
+            }
+            CallNear => {
+                let (op, size) = CallOp::try_decode_indirect(bytes, false)?;
+                Ok((Instruction::Call(op), size))
+            }
+            CallFar => {
+                let (op, size) = CallOp::try_decode_indirect(bytes, true)?;
+                Ok((Instruction::Call(op), size))
+            }
+            JumpNear => {
+                let (op, size) = JumpOp::try_decode_indirect(bytes, false)?;
+                Ok((Instruction::Jump(op), size))
+            }
+            JumpFar => {
+                let (op, size) = JumpOp::try_decode_indirect(bytes, true)?;
+                Ok((Instruction::Jump(op), size))
+            }
+            Push => {
+                let (op, size) = PushOp::try_decode_rm(bytes)?;
+                Ok((Instruction::Push(op), size))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_decode_lenient() {
+        // mov cx, 3; two bytes that do not start an instruction; inc cx
+        let buffer = vec![0xb9, 0x03, 0x00, 0x60, 0xf1, 0x41];
+        assert!(Decoder::new(buffer.clone()).decode().is_err());
+
+        let iqueue = Decoder::new(buffer).decode_lenient();
+        assert_eq!(iqueue.undecoded(), 2);
+        assert_eq!(iqueue.to_string(), "mov cx, 3\ndb 0x60\ndb 0xf1\ninc cx\n");
+    }
+
+    #[test]
+    fn test_decode_truncated() {
+        // mov word [bx + 1000], 4660 is six bytes long.
+        let bytes = [0xc7, 0x87, 0xe8, 0x03, 0x34, 0x12];
+        for len in 0..bytes.len() {
+            let err = Decoder::try_decode_next(&bytes[..len]).unwrap_err();
+            match err.kind {
+                DecodeErrorKind::UnexpectedEof { needed } => {
+                    assert!(needed > 0 && len + needed <= bytes.len());
+                }
+                kind => panic!("Expected unexpected end of input, got {kind:?}"),
+            }
+            assert_eq!(err.bytes, &bytes[..len]);
+        }
+        assert_eq!(Decoder::try_decode_next(&bytes).unwrap().1, 6);
+
+        // The bytes of the error include the prefixes of the instruction.
+        let bytes = [0x26, 0xc7, 0x87, 0xe8];
+        let err = Decoder::try_decode_next(&bytes).unwrap_err();
+        assert_eq!(err.kind, DecodeErrorKind::UnexpectedEof { needed: 1 });
+        assert_eq!(err.bytes, bytes);
+    }
+
+    #[test]
+    fn test_decode_error_location() {
+        // mov cx, 3; inc cx; an undefined OPCODE
+        let err = Decoder::new(vec![0xb9, 0x03, 0x00, 0x41, 0x60])
+            .decode()
+            .unwrap_err();
+        assert_eq!(err.offset, 4);
+        assert_eq!(err.bytes, [0x60]);
+        assert_eq!(
+            err.kind,
+            DecodeErrorKind::InvalidField {
+                field: Field::OpCode,
+                value: 0x60
+            }
+        );
+
+        // mov ax, 1; an undefined operation of group 5, behind a segment override
+        let err = Decoder::new(vec![0xb8, 0x01, 0x00, 0x26, 0xff, 0x38])
+            .decode()
+            .unwrap_err();
+        assert_eq!(err.offset, 3);
+        assert_eq!(err.bytes, [0x26, 0xff, 0x38]);
+        assert_eq!(
+            err.kind,
+            DecodeErrorKind::InvalidField {
+                field: Field::Reg,
+                value: 0b111
+            }
+        );
+
+        // rep inc cx
+        let err = Decoder::new(vec![0xf3, 0x41]).decode().unwrap_err();
+        assert_eq!(err.bytes, [0xf3, 0x41]);
+        assert!(matches!(err.kind, DecodeErrorKind::InvalidEncoding(_)));
+    }
+
+    #[test]
+    fn test_decode_long_prefix_runs() {
+        // es: repeated up to the limit before mov [bx], ax
+        let mut bytes = vec![0x26; MAX_PREFIXES];
+        bytes.extend([0x89, 0x07]);
+        let (instruction, size) = Decoder::try_decode_next(&bytes).unwrap();
+        assert_eq!(size, bytes.len());
+        assert_eq!(instruction.to_string(), "mov es:[bx], ax");
+
+        // A long run of lock or es: prefixes is an error, not a stack overflow.
+        for prefix in [0xf0, 0x26] {
+            let mut bytes = vec![prefix; 1000];
+            bytes.extend([0x89, 0x07]);
+            let err = Decoder::try_decode_next(&bytes).unwrap_err();
+            assert!(matches!(err.kind, DecodeErrorKind::InvalidEncoding(_)));
+            assert_eq!(err.bytes, vec![prefix; MAX_PREFIXES + 1]);
+
+            let queue = Decoder::new(bytes).decode_lenient();
+            assert_eq!(queue.undecoded(), 1000 - MAX_PREFIXES);
+        }
+    }
+
+    #[test]
+    fn test_decode_arbitrary_prefixes() {
+        // A small xorshift generator, so that the test is deterministic.
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        let mut next_byte = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 24) as u8
+        };
+
+        // Displacements and immediates at the edges of their ranges, such as
+        // `i16::MIN` and `i8::MIN`, which are easy to mishandle when formatting.
+        const EDGE_TAILS: [[u8; 4]; 4] = [
+            [0x00, 0x80, 0x00, 0x80],
+            [0xff, 0x7f, 0xff, 0x7f],
+            [0x80, 0x80, 0x80, 0x80],
+            [0x7f, 0x00, 0x80, 0xff],
+        ];
+
+        // Every combination of the first two bytes, followed by random bytes or
+        // edge values, and every prefix of it from one to six bytes long.
+        for first in 0..=0xffffu32 {
+            let mut random = [0u8; 4];
+            for byte in &mut random {
+                *byte = next_byte();
+            }
+            for tail in std::iter::once(random).chain(EDGE_TAILS) {
+                let mut bytes = [0u8; 6];
+                bytes[..2].copy_from_slice(&(first as u16).to_be_bytes());
+                bytes[2..].copy_from_slice(&tail);
+                for len in 1..=bytes.len() {
+                    if let Ok((instruction, size)) = Decoder::try_decode_next(&bytes[..len]) {
+                        assert!(size > 0 && size <= len, "{:02x?}", &bytes[..len]);
+                        let _ = instruction.to_string();
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_display_word_immediate_min() {
+        let (instruction, size) = Decoder::try_decode_next(&[0x81, 0x27, 0x00, 0x80]).unwrap();
+        assert_eq!(size, 4);
+        assert_eq!(instruction.to_string(), "and word [bx], -32768");
+    }
+
+    #[test]
+    fn test_display_esc() {
+        let cases: [(&[u8], &str); 4] = [
+            (&[0xd8, 0xc1], "db 0xd8, 0xc1"),
+            (&[0xdb, 0x87, 0x00, 0x80], "db 0xdb, 0x87, 0x00, 0x80"),
+            (&[0x26, 0xde, 0x1c], "db 0x26, 0xde, 0x1c"),
+            (
+                &[0xf0, 0x26, 0xdf, 0x7f, 0xff],
+                "db 0xf0, 0x26, 0xdf, 0x7f, 0xff",
+            ),
+        ];
+        for (bytes, asm) in cases {
+            let (instruction, size) = Decoder::try_decode_next(bytes).unwrap();
+            assert_eq!(size, bytes.len());
+            assert_eq!(instruction.to_string(), asm);
+        }
+    }
 }
@@ -1,10 +1,151 @@
-#[derive(Debug)]
-pub enum DecodeError {
-    Mode,
-    NumType,
-    SegmentRegister,
-    OpCode(String),
-    Displacement,
+use std::{error, fmt};
+
+/// A field of an encoded instruction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    /// The OPCODE, held in the first byte of the instruction.
+    OpCode,
+    /// The MOD field of the ModRM byte.
+    Mod,
+    /// The REG field of the ModRM byte, which also selects the operation of
+    /// group OPCODEs and the segment register of segment instructions.
+    Reg,
+    /// The R/M field of the ModRM byte.
+    RM,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OpCode => write!(f, "OPCODE"),
+            Self::Mod => write!(f, "MOD"),
+            Self::Reg => write!(f, "REG"),
+            Self::RM => write!(f, "R/M"),
+        }
+    }
+}
+
+/// The reason an instruction could not be decoded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DecodeErrorKind {
+    /// A field holds a value that is not valid for the instruction.
+    InvalidField { field: Field, value: u8 },
+    /// The byte-code ended in the middle of an instruction, `needed` bytes short.
+    UnexpectedEof { needed: usize },
+    /// The bytes do not form a valid encoding of the instruction.
+    InvalidEncoding(&'static str),
+}
+
+impl fmt::Display for DecodeErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidField {
+                field: Field::OpCode,
+                value,
+            } => write!(f, "unknown OPCODE {value:#010b}"),
+            Self::InvalidField {
+                field: Field::Mod,
+                value,
+            } => write!(f, "invalid MOD field {value:#04b}"),
+            Self::InvalidField { field, value } => write!(f, "invalid {field} field {value:#05b}"),
+            Self::UnexpectedEof { needed } => {
+                write!(f, "unexpected end of input, {needed} more byte(s) needed")
+            }
+            Self::InvalidEncoding(reason) => write!(f, "invalid encoding, {reason}"),
+        }
+    }
+}
+
+/// An error that occurred while decoding byte-code.
+///
+/// Besides the reason of the failure, the error records where the instruction
+/// that failed to decode starts and the bytes of it that were involved: every
+/// byte up to the one holding the invalid field, or all the remaining bytes if
+/// the byte-code ended early.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+    pub kind: DecodeErrorKind,
+}
+
+impl DecodeError {
+    /// Creates an error for an invalid field value.
+    #[inline]
+    pub fn invalid_field(field: Field, value: u8) -> Self {
+        Self::from(DecodeErrorKind::InvalidField { field, value })
+    }
+
+    /// Creates an error for an invalid encoding.
+    #[inline]
+    pub fn invalid_encoding(reason: &'static str) -> Self {
+        Self::from(DecodeErrorKind::InvalidEncoding(reason))
+    }
+
+    /// Returns the error of the instruction that follows a prefix byte, as an
+    /// error of the prefixed instruction.
+    pub(crate) fn prefixed(mut self, prefix: u8) -> Self {
+        self.bytes.insert(0, prefix);
+        self
+    }
+}
+
+impl From<DecodeErrorKind> for DecodeError {
+    fn from(kind: DecodeErrorKind) -> Self {
+        Self {
+            offset: 0,
+            bytes: Vec::new(),
+            kind,
+        }
+    }
 }
 
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {:#06x}", self.kind, self.offset)?;
+        if !self.bytes.is_empty() {
+            let bytes = self
+                .bytes
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<Vec<_>>()
+                .join(" ");
+            write!(f, " (bytes: {bytes})")?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for DecodeError {}
+
 pub type DResult<I> = Result<(I, usize), DecodeError>;
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_display_decode_error() {
+        let mut err = DecodeError::invalid_field(Field::OpCode, 0x60);
+        err.offset = 0x12;
+        err.bytes = vec![0x60];
+        assert_eq!(
+            err.to_string(),
+            "unknown OPCODE 0b01100000 at offset 0x0012 (bytes: 60)"
+        );
+
+        let mut err = DecodeError::invalid_field(Field::Reg, 0b111);
+        err.bytes = vec![0x26, 0xff, 0xf8];
+        assert_eq!(
+            err.to_string(),
+            "invalid REG field 0b111 at offset 0x0000 (bytes: 26 ff f8)"
+        );
+
+        let err = DecodeError::from(DecodeErrorKind::UnexpectedEof { needed: 2 });
+        assert_eq!(
+            err.to_string(),
+            "unexpected end of input, 2 more byte(s) needed at offset 0x0000"
+        );
+    }
+}
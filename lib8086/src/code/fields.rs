@@ -1,10 +1,10 @@
-use super::{get_bit, DecodeError};
+use super::{get_bit, DecodeError, Field};
 
 /// `Width` is an enum that represents the `W` field.
 ///
 /// The `W` field distinguishes between byte and word operation.
 /// If `W = 0` the operation acts on a byte; otherwise on a word.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Width {
     Byte,
     Word,
@@ -133,7 +133,7 @@ impl TryFrom<u8> for Mode {
             0b01 => Ok(Self::Memory8),
             0b10 => Ok(Self::Memory16),
             0b11 => Ok(Self::Register),
-            _ => Err(DecodeError::Mode),
+            _ => Err(DecodeError::invalid_field(Field::Mod, code)),
         }
     }
 }
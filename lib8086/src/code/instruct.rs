@@ -1,25 +1,109 @@
 use std::fmt;
 
+use super::ops::control::write_esc;
 use super::ops::*;
+use super::{Operand, SegmentRegister};
 
 #[derive(Debug)]
 pub enum Instruction {
     Mov(MovOp),
+    Transfer(TransferOp),
     Push(PushOp),
     Pop(PopOp),
     Num(NumOp),
+    Adjust(AdjustOp),
+    Logic(LogicOp),
+    Shift(ShiftOp),
+    MulDiv(MulDivOp),
+    String(StringOp),
     CondJump(CondJumpOp),
+    Jump(JumpOp),
+    Call(CallOp),
+    Ret(RetOp),
+    Interrupt(InterruptOp),
+    Io(IoOp),
+    Control(ControlOp),
+    Convert(ConvertOp),
+    /// An instruction preceded by the LOCK prefix, which asserts the bus lock
+    /// signal while it executes.
+    Lock(Box<Instruction>),
+    /// A byte that could not be decoded as an instruction, such as data mixed
+    /// in with the code.
+    Data(u8),
     Halt,
 }
 
+impl Instruction {
+    /// Applies a segment override prefix to the memory operand of the instruction.
+    ///
+    /// Returns whether the instruction has an operand whose segment can be
+    /// overridden.
+    pub fn set_segment(&mut self, segment: SegmentRegister) -> bool {
+        let (first, second): (Option<&mut Operand>, Option<&mut Operand>) = match self {
+            Self::Mov(op) => (Some(&mut op.source), Some(&mut op.destination)),
+            Self::Transfer(TransferOp::Xlat { segment: xlat }) => {
+                *xlat = Some(segment);
+                return true;
+            }
+            Self::Transfer(op) => op.operands_mut(),
+            Self::Push(op) => (Some(&mut op.operand), None),
+            Self::Pop(op) => (Some(&mut op.operand), None),
+            Self::Num(op) => {
+                let (source, destination) = op.operands_mut();
+                (source, Some(destination))
+            }
+            Self::Logic(op) => {
+                let (source, destination) = op.operands_mut();
+                (source, Some(destination))
+            }
+            Self::Shift(op) => (Some(&mut op.destination), None),
+            Self::MulDiv(op) => (Some(&mut op.source), None),
+            Self::Jump(op) => (op.target.operand_mut(), None),
+            Self::Call(op) => (op.target.operand_mut(), None),
+            Self::Control(ControlOp::Esc { source, .. }) => (Some(source), None),
+            Self::Lock(instruction) => return instruction.set_segment(segment),
+            Self::String(op) if op.uses_source() => {
+                op.segment = Some(segment);
+                return true;
+            }
+            _ => (None, None),
+        };
+        [first, second]
+            .into_iter()
+            .flatten()
+            .any(|operand| operand.set_segment(segment))
+    }
+}
+
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Mov(op) => write!(f, "{op}"),
+            Self::Transfer(op) => write!(f, "{op}"),
             Self::Push(op) => write!(f, "{op}"),
             Self::Pop(op) => write!(f, "{op}"),
             Self::Num(op) => write!(f, "{op}"),
+            Self::Adjust(op) => write!(f, "{op}"),
+            Self::Logic(op) => write!(f, "{op}"),
+            Self::Shift(op) => write!(f, "{op}"),
+            Self::MulDiv(op) => write!(f, "{op}"),
+            Self::String(op) => write!(f, "{op}"),
             Self::CondJump(op) => write!(f, "{op}"),
+            Self::Jump(op) => write!(f, "{op}"),
+            Self::Call(op) => write!(f, "{op}"),
+            Self::Ret(op) => write!(f, "{op}"),
+            Self::Interrupt(op) => write!(f, "{op}"),
+            Self::Io(op) => write!(f, "{op}"),
+            Self::Control(op) => write!(f, "{op}"),
+            Self::Convert(op) => write!(f, "{op}"),
+            // NASM does not accept a prefix before `db`, so it is written as data.
+            Self::Lock(instruction) => match &**instruction {
+                Self::Control(ControlOp::Esc {
+                    source, encoding, ..
+                }) => write_esc(f, &[0xf0], source, encoding),
+                instruction => write!(f, "lock {instruction}"),
+            },
+            Self::Data(byte) => write!(f, "db 0x{byte:02x}"),
             Self::Halt => write!(f, ""),
         }
     }
@@ -47,6 +131,14 @@ impl InstructionQueue {
         self.byte_offsets.push(self.next_offset);
         self.next_offset += size;
     }
+
+    /// Returns the number of bytes that could not be decoded as instructions.
+    pub fn undecoded(&self) -> usize {
+        self.inner
+            .iter()
+            .filter(|instr| matches!(instr, Instruction::Data(_)))
+            .count()
+    }
 }
 
 impl fmt::Display for InstructionQueue {
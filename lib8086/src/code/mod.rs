@@ -1,8 +1,8 @@
 pub mod error;
-pub use error::{DResult, DecodeError};
+pub use error::{DResult, DecodeError, DecodeErrorKind, Field};
 
 pub mod address;
-pub use address::{Displacement, EffectiveAddr};
+pub use address::{Displacement, EffectiveAddr, MemoryAddr};
 
 pub mod register;
 pub use register::{Register, SegmentRegister};
@@ -28,6 +28,33 @@ pub fn get_bit(byte: u8, pos: u8) -> bool {
     (byte >> pos) & 1 == 1
 }
 
+/// Returns the byte at position `idx` of the byte-code stream.
+///
+/// If the stream ends before it, an `UnexpectedEof` error is returned instead.
+#[inline]
+pub fn byte_at(bytes: &[u8], idx: usize) -> Result<u8, DecodeError> {
+    match bytes.get(idx) {
+        Some(&byte) => Ok(byte),
+        None => Err(DecodeError::from(DecodeErrorKind::UnexpectedEof {
+            needed: idx + 1 - bytes.len(),
+        })),
+    }
+}
+
+/// Returns the two bytes of the little-endian word at position `idx` of the
+/// byte-code stream.
+///
+/// If the stream ends before them, an `UnexpectedEof` error is returned instead.
+#[inline]
+pub fn word_at(bytes: &[u8], idx: usize) -> Result<[u8; 2], DecodeError> {
+    match bytes.get(idx..idx + 2) {
+        Some(&[low, high]) => Ok([low, high]),
+        _ => Err(DecodeError::from(DecodeErrorKind::UnexpectedEof {
+            needed: idx + 2 - bytes.len(),
+        })),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -44,4 +71,16 @@ mod tests {
         assert!(!get_bit(byte, 4));
         assert!(!get_bit(byte, 6));
     }
+
+    #[test]
+    fn test_bounds_checked_reads() {
+        let bytes = [0x01, 0x02, 0x03];
+        assert_eq!(byte_at(&bytes, 2).unwrap(), 0x03);
+        assert_eq!(word_at(&bytes, 1).unwrap(), [0x02, 0x03]);
+
+        let eof = |needed| DecodeError::from(DecodeErrorKind::UnexpectedEof { needed });
+        assert_eq!(byte_at(&bytes, 3).unwrap_err(), eof(1));
+        assert_eq!(word_at(&bytes, 2).unwrap_err(), eof(1));
+        assert_eq!(word_at(&bytes, 4).unwrap_err(), eof(3));
+    }
 }
@@ -1,8 +1,8 @@
 use std::fmt;
 
 use super::{
-    DResult, Direction, Displacement, EffectiveAddr, Mode, Reg, Register, SegmentRegister, Width,
-    RM,
+    DResult, Direction, Displacement, EffectiveAddr, MemoryAddr, Mode, Reg, Register,
+    SegmentRegister, Width, RM,
 };
 use crate::value::Value;
 
@@ -14,7 +14,7 @@ use crate::value::Value;
 pub enum Operand {
     Register(Register),
     SegmentRegister(SegmentRegister),
-    Memory(EffectiveAddr),
+    Memory(MemoryAddr),
     Immediate(Value),
 }
 
@@ -28,7 +28,7 @@ impl Operand {
     /// Creates a new memory operand from R/M field and `Displacement`.
     #[inline]
     pub fn memory(rm: u8, disp: Displacement) -> Operand {
-        Self::Memory(EffectiveAddr::new(rm, disp))
+        Self::Memory(MemoryAddr::new(EffectiveAddr::new(rm, disp)))
     }
 
     /// Creates an immediate value operand from a `Value`.
@@ -37,6 +37,33 @@ impl Operand {
         Self::Immediate(value)
     }
 
+    /// Returns the width of the operand, if it can be inferred from the
+    /// operand alone.
+    ///
+    /// Memory operands do not carry a width; it is determined by the instruction
+    /// or the other operand.
+    pub fn width(&self) -> Option<Width> {
+        match self {
+            Self::Register(reg) => Some(reg.width()),
+            Self::SegmentRegister(_) => Some(Width::Word),
+            Self::Immediate(Value::Byte(_)) => Some(Width::Byte),
+            Self::Immediate(Value::Word(_)) => Some(Width::Word),
+            Self::Memory(_) => None,
+        }
+    }
+
+    /// Sets the segment override of a memory operand, returning whether the
+    /// operand is in memory.
+    pub fn set_segment(&mut self, segment: SegmentRegister) -> bool {
+        match self {
+            Self::Memory(mem) => {
+                mem.segment = Some(segment);
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Creates a register or memory operand.
     pub fn register_or_memory(width: bool, mode: &Mode, rm: u8, bytes: &[u8]) -> DResult<Self> {
         match mode {
@@ -74,7 +101,7 @@ impl fmt::Display for Operand {
             Register(reg) => write!(f, "{reg}"),
             SegmentRegister(segreg) => write!(f, "{segreg}"),
             Immediate(val) => write!(f, "{val}"),
-            Memory(addr) => write!(f, "{addr}"),
+            Memory(mem) => write!(f, "{mem}"),
         }
     }
 }
@@ -84,7 +111,34 @@ pub fn get_prefix<'a>(source: &'a Operand, dest: &'a Operand) -> &'a str {
     use Value::*;
     match (source, dest) {
         (Immediate(Byte(_)), Memory(_)) => "byte ",
-        (Immediate(Word(val)), Memory(_)) if val.abs() <= i8::MAX as i16 => "word ",
+        (Immediate(Word(val)), Memory(_)) if val.unsigned_abs() <= i8::MAX as u16 => "word ",
+        _ => "",
+    }
+}
+
+/// Returns the size prefix of a memory destination operand whose source operand
+/// is an immediate value.
+///
+/// Unlike `get_prefix`, the size is always given, since an immediate value does
+/// not tell the assembler the size of the operation.
+pub fn get_immediate_prefix(source: &Operand, dest: &Operand) -> &'static str {
+    use Operand::*;
+    use Value::*;
+    match (source, dest) {
+        (Immediate(Byte(_)), Memory(_)) => "byte ",
+        (Immediate(Word(_)), Memory(_)) => "word ",
+        _ => "",
+    }
+}
+
+/// Returns the size prefix of the single operand of an instruction.
+///
+/// Memory operands need an explicit size, since it cannot be inferred from
+/// another operand.
+pub fn get_unary_prefix(operand: &Operand, width: &Width) -> &'static str {
+    match (operand, width) {
+        (Operand::Memory(_), Width::Byte) => "byte ",
+        (Operand::Memory(_), Width::Word) => "word ",
         _ => "",
     }
 }
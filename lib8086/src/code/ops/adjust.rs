@@ -0,0 +1,58 @@
+use std::fmt;
+
+use crate::code::{byte_at, DResult, DecodeError, Field};
+
+/// The base that AAM and AAD use when no other base is encoded, which is the
+/// only one documented for the 8086.
+const DECIMAL_BASE: u8 = 10;
+
+/// A decimal adjust instruction, which corrects the result of an arithmetic
+/// operation on packed or unpacked BCD values in the accumulator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdjustOp {
+    /// Decimal adjust for addition of packed BCD values.
+    Daa,
+    /// Decimal adjust for subtraction of packed BCD values.
+    Das,
+    /// ASCII adjust for addition of unpacked BCD values.
+    Aaa,
+    /// ASCII adjust for subtraction of unpacked BCD values.
+    Aas,
+    /// ASCII adjust for multiplication, in the specified base.
+    Aam(u8),
+    /// ASCII adjust for division, in the specified base.
+    Aad(u8),
+}
+
+impl AdjustOp {
+    /// Tries to decode a decimal adjust instruction.
+    ///
+    /// AAM and AAD are followed by a byte holding the base, which is 10 for the
+    /// documented forms of the instructions.
+    pub fn try_decode(bytes: &[u8]) -> DResult<Self> {
+        match bytes[0] {
+            0x27 => Ok((Self::Daa, 1)),
+            0x2F => Ok((Self::Das, 1)),
+            0x37 => Ok((Self::Aaa, 1)),
+            0x3F => Ok((Self::Aas, 1)),
+            0xD4 => Ok((Self::Aam(byte_at(bytes, 1)?), 2)),
+            0xD5 => Ok((Self::Aad(byte_at(bytes, 1)?), 2)),
+            byte => Err(DecodeError::invalid_field(Field::OpCode, byte)),
+        }
+    }
+}
+
+impl fmt::Display for AdjustOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Daa => write!(f, "daa"),
+            Self::Das => write!(f, "das"),
+            Self::Aaa => write!(f, "aaa"),
+            Self::Aas => write!(f, "aas"),
+            Self::Aam(DECIMAL_BASE) => write!(f, "aam"),
+            Self::Aam(base) => write!(f, "aam {base}"),
+            Self::Aad(DECIMAL_BASE) => write!(f, "aad"),
+            Self::Aad(base) => write!(f, "aad {base}"),
+        }
+    }
+}
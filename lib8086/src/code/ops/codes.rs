@@ -1,4 +1,4 @@
-use crate::code::DecodeError;
+use crate::code::{DecodeError, Field};
 
 macro_rules! create_opcodes {
     (
@@ -7,22 +7,188 @@ macro_rules! create_opcodes {
         )+
     ) => {
 
-        #[derive(Debug)]
+        #[derive(Debug, Clone, Copy, PartialEq)]
         pub enum OpCode {
             $($name,)+
         }
 
-        impl OpCode {
-            /// Parses a byte and extracts an instruction OPCODE.
-            pub fn parse(byte: u8) -> Result<Self, DecodeError> {
-                let bstr = format!("{:08b}", byte);
+        /// The bit patterns of all OPCODEs, as written in the instruction manual.
+        ///
+        /// A pattern is a prefix of the first byte of the instruction; the rest of
+        /// the bits encode fields such as the width, direction or register.
+        const PATTERNS: &[(OpCode, &str)] = &[$((OpCode::$name, $code_str),)+];
+    }
+}
+
+/// Lookup table from the first byte of an instruction to its OPCODE.
+///
+/// The table is built at compile time from `PATTERNS`. When several patterns
+/// match a byte the longest, most specific, one wins, so the order in which the
+/// patterns are listed does not matter.
+static OPCODES: [Option<OpCode>; 256] = build_opcode_table(PATTERNS);
+
+/// Returns whether the binary pattern is a prefix of the byte.
+const fn matches(byte: u8, pattern: &str) -> bool {
+    let bits = pattern.as_bytes();
+    let mut i = 0;
+    while i < bits.len() {
+        let bit = (byte >> (7 - i)) & 1;
+        let expected = match bits[i] {
+            b'0' => 0,
+            b'1' => 1,
+            _ => panic!("OPCODE patterns may only contain '0' and '1'."),
+        };
+        if bit != expected {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
 
-                match bstr {
-                    $(s if s.starts_with($code_str) => Ok(Self::$name),)+
-                    _ => Err(DecodeError::OpCode(bstr))
+const fn build_opcode_table(patterns: &[(OpCode, &str)]) -> [Option<OpCode>; 256] {
+    let mut table = [None; 256];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut longest = 0;
+        let mut i = 0;
+        while i < patterns.len() {
+            let (opcode, pattern) = patterns[i];
+            if matches(byte as u8, pattern) {
+                if pattern.len() == longest {
+                    panic!("Two OPCODE patterns of equal length match the same byte.");
+                }
+                if pattern.len() > longest {
+                    longest = pattern.len();
+                    table[byte] = Some(opcode);
                 }
             }
+            i += 1;
         }
+        byte += 1;
+    }
+    table
+}
+
+impl OpCode {
+    /// Parses a byte and extracts an instruction OPCODE.
+    #[inline]
+    pub fn parse(byte: u8) -> Result<Self, DecodeError> {
+        OPCODES[byte as usize].ok_or_else(|| DecodeError::invalid_field(Field::OpCode, byte))
+    }
+}
+
+#[cfg(feature = "legacy-opcodes")]
+impl OpCode {
+    /// Parses a byte and extracts an instruction OPCODE by matching its binary
+    /// representation against the patterns in turn, the first match winning.
+    ///
+    /// This is how OPCODEs were parsed before the lookup table, which relied on
+    /// more specific patterns being listed first. It is kept to compare both in
+    /// the `decode` benchmark.
+    pub fn parse_pattern(byte: u8) -> Result<Self, DecodeError> {
+        let bstr = format!("{:08b}", byte);
+        PATTERNS
+            .iter()
+            .find(|(_, pattern)| bstr.starts_with(pattern))
+            .map(|&(opcode, _)| opcode)
+            .ok_or_else(|| DecodeError::invalid_field(Field::OpCode, byte))
+    }
+}
+
+/// Operations of group OPCODEs, which are selected by the REG field of the
+/// ModRM byte rather than by the first byte alone.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupOpCode {
+    Add,
+    Or,
+    Adc,
+    Sbb,
+    And,
+    Sub,
+    Xor,
+    Cmp,
+    Test,
+    Not,
+    Neg,
+    Mul,
+    Imul,
+    Div,
+    Idiv,
+    Inc,
+    Dec,
+    CallNear,
+    CallFar,
+    JumpNear,
+    JumpFar,
+    Push,
+}
+
+/// Immediate to register/memory arithmetic and logic (`0x80` - `0x83`).
+static GROUP1: [Option<GroupOpCode>; 8] = {
+    use GroupOpCode::*;
+    [
+        Some(Add),
+        Some(Or),
+        Some(Adc),
+        Some(Sbb),
+        Some(And),
+        Some(Sub),
+        Some(Xor),
+        Some(Cmp),
+    ]
+};
+
+/// Unary arithmetic, multiplication and division (`0xF6`, `0xF7`).
+static GROUP3: [Option<GroupOpCode>; 8] = {
+    use GroupOpCode::*;
+    [
+        Some(Test),
+        None,
+        Some(Not),
+        Some(Neg),
+        Some(Mul),
+        Some(Imul),
+        Some(Div),
+        Some(Idiv),
+    ]
+};
+
+/// Byte increment and decrement (`0xFE`).
+static GROUP4: [Option<GroupOpCode>; 8] = {
+    use GroupOpCode::*;
+    [Some(Inc), Some(Dec), None, None, None, None, None, None]
+};
+
+/// Word increment and decrement, indirect calls and jumps and push (`0xFF`).
+static GROUP5: [Option<GroupOpCode>; 8] = {
+    use GroupOpCode::*;
+    [
+        Some(Inc),
+        Some(Dec),
+        Some(CallNear),
+        Some(CallFar),
+        Some(JumpNear),
+        Some(JumpFar),
+        Some(Push),
+        None,
+    ]
+};
+
+impl GroupOpCode {
+    /// Parses the first two bytes of a group instruction and extracts the
+    /// operation selected by the REG field of the ModRM byte.
+    #[inline]
+    pub fn parse(byte: u8, modrm: u8) -> Result<Self, DecodeError> {
+        let table = match OpCode::parse(byte)? {
+            OpCode::NumImRM => &GROUP1,
+            OpCode::Group3 => &GROUP3,
+            OpCode::Group4 => &GROUP4,
+            OpCode::Group5 => &GROUP5,
+            _ => return Err(DecodeError::invalid_field(Field::OpCode, byte)),
+        };
+        let reg = (modrm >> 3) & 0b111;
+        table[reg as usize].ok_or_else(|| DecodeError::invalid_field(Field::Reg, reg))
     }
 }
 
@@ -34,6 +200,17 @@ create_opcodes! {
     (MovRMSegReg, "10001110");
     (MovSegRegRM, "10001100");
 
+    (XchgRMReg, "1000011");
+    (XchgAcc, "10010");
+    (Xlat, "11010111");
+    (Lea, "10001101");
+    (Lds, "11000101");
+    (Les, "11000100");
+    (Lahf, "10011111");
+    (Sahf, "10011110");
+    (Pushf, "10011100");
+    (Popf, "10011101");
+
     (NumImRM, "100000");
 
     (AddRMReg, "000000");
@@ -43,11 +220,32 @@ create_opcodes! {
     (CmpRMReg, "001110");
 
     (AddImAcc, "0000010");
-    (AdcImAcc, "0000010");
+    (AdcImAcc, "0001010");
     (SubImAcc, "0010110");
     (SbbImAcc, "0001110");
     (CmpImAcc, "0011110");
 
+    (Daa, "00100111");
+    (Das, "00101111");
+    (Aaa, "00110111");
+    (Aas, "00111111");
+    (Aam, "11010100");
+    (Aad, "11010101");
+
+    (AndRMReg, "001000");
+    (OrRMReg, "000010");
+    (XorRMReg, "001100");
+    (TestRMReg, "1000010");
+
+    (AndImAcc, "0010010");
+    (OrImAcc, "0000110");
+    (XorImAcc, "0011010");
+    (TestImAcc, "1010100");
+
+    (Group3, "1111011");
+
+    (ShiftRotate, "110100");
+
     (JumpEqual, "01110100");
     (JumpLess, "01111100");
     (JumpLessEq, "01111110");
@@ -69,7 +267,54 @@ create_opcodes! {
     (LoopEqual, "11100001");
     (LoopNEqual, "11100000");
 
-    (PushRegRM, "11111111");
+    (JumpShort, "11101011");
+    (JumpNear, "11101001");
+    (JumpFar, "11101010");
+    (CallNear, "11101000");
+    (CallFar, "10011010");
+    (Ret, "1100001");
+    (RetFar, "1100101");
+
+    (Movs, "1010010");
+    (Cmps, "1010011");
+    (Stos, "1010101");
+    (Lods, "1010110");
+    (Scas, "1010111");
+    (Rep, "1111001");
+
+    (InIm, "1110010");
+    (OutIm, "1110011");
+    (InDX, "1110110");
+    (OutDX, "1110111");
+
+    (Clc, "11111000");
+    (Stc, "11111001");
+    (Cmc, "11110101");
+    (Cld, "11111100");
+    (Std, "11111101");
+    (Cli, "11111010");
+    (Sti, "11111011");
+    (Wait, "10011011");
+    (Esc, "11011");
+    (Lock, "11110000");
+
+    (Cbw, "10011000");
+    (Cwd, "10011001");
+
+    (Int3, "11001100");
+    (Int, "11001101");
+    (Into, "11001110");
+    (Iret, "11001111");
+
+    (SegOverrideES, "00100110");
+    (SegOverrideCS, "00101110");
+    (SegOverrideSS, "00110110");
+    (SegOverrideDS, "00111110");
+
+    (IncReg, "01000");
+    (DecReg, "01001");
+    (Group4, "11111110");
+    (Group5, "11111111");
     (PushReg, "01010");
     (PopRegRM, "10001111");
     (PopReg, "01011");
@@ -77,3 +322,71 @@ create_opcodes! {
 
     (Halt, "11110100");
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_opcode_table() {
+        assert_eq!(OpCode::parse(0x89).unwrap(), OpCode::MovRegRM);
+        assert_eq!(OpCode::parse(0xb9).unwrap(), OpCode::MovImReg);
+        assert_eq!(OpCode::parse(0x00).unwrap(), OpCode::AddRMReg);
+        assert_eq!(OpCode::parse(0x06).unwrap(), OpCode::PushPopSeg);
+        assert_eq!(OpCode::parse(0x26).unwrap(), OpCode::SegOverrideES);
+        assert_eq!(OpCode::parse(0x27).unwrap(), OpCode::Daa);
+        assert_eq!(OpCode::parse(0xd4).unwrap(), OpCode::Aam);
+        assert_eq!(OpCode::parse(0xd7).unwrap(), OpCode::Xlat);
+        assert_eq!(OpCode::parse(0xf4).unwrap(), OpCode::Halt);
+        assert!(OpCode::parse(0x0f).is_ok());
+        assert!(OpCode::parse(0x60).is_err());
+        assert!(OpCode::parse(0xf1).is_err());
+    }
+
+    #[test]
+    fn test_opcode_table_matches_patterns() {
+        // Every byte must decode to the longest pattern that is a prefix of it.
+        for byte in 0..=255u8 {
+            let bstr = format!("{:08b}", byte);
+            let expected = PATTERNS
+                .iter()
+                .filter(|(_, pattern)| bstr.starts_with(pattern))
+                .max_by_key(|(_, pattern)| pattern.len())
+                .map(|(opcode, _)| *opcode);
+            assert_eq!(OpCode::parse(byte).ok(), expected, "{bstr}");
+        }
+    }
+
+    #[cfg(feature = "legacy-opcodes")]
+    #[test]
+    fn test_parse_pattern() {
+        for byte in 0..=255u8 {
+            assert_eq!(OpCode::parse_pattern(byte).ok(), OpCode::parse(byte).ok());
+        }
+    }
+
+    #[test]
+    fn test_group_table() {
+        assert_eq!(
+            GroupOpCode::parse(0x83, 0b11_111_000).unwrap(),
+            GroupOpCode::Cmp
+        );
+        assert_eq!(
+            GroupOpCode::parse(0x80, 0b00_100_111).unwrap(),
+            GroupOpCode::And
+        );
+        assert_eq!(
+            GroupOpCode::parse(0xf7, 0b11_011_000).unwrap(),
+            GroupOpCode::Neg
+        );
+        assert_eq!(
+            GroupOpCode::parse(0xff, 0b01_110_110).unwrap(),
+            GroupOpCode::Push
+        );
+        assert!(GroupOpCode::parse(0xf6, 0b00_001_000).is_err());
+        assert!(GroupOpCode::parse(0xfe, 0b00_010_000).is_err());
+        assert!(GroupOpCode::parse(0xff, 0b00_111_000).is_err());
+        assert!(GroupOpCode::parse(0x89, 0b00_000_000).is_err());
+    }
+}
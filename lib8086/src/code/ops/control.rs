@@ -0,0 +1,146 @@
+use std::fmt;
+
+use crate::code::fields::*;
+use crate::code::{byte_at, DResult, DecodeError, Field, MemoryAddr, Operand};
+
+/// A flag or processor control operation.
+#[derive(Debug)]
+pub enum ControlOp {
+    /// Clears the carry flag.
+    Clc,
+    /// Sets the carry flag.
+    Stc,
+    /// Complements the carry flag.
+    Cmc,
+    /// Clears the direction flag.
+    Cld,
+    /// Sets the direction flag.
+    Std,
+    /// Clears the interrupt-enable flag.
+    Cli,
+    /// Sets the interrupt-enable flag.
+    Sti,
+    /// Waits for the TEST input to be asserted by a coprocessor.
+    Wait,
+    /// Passes an instruction to a coprocessor, along with its operand.
+    ///
+    /// The 6-bit opcode is formed by the three least significant bits of the
+    /// instruction's first byte followed by the REG field. The encoding holds the
+    /// instruction's bytes without prefixes, since only the coprocessor knows the
+    /// mnemonic they stand for.
+    Esc {
+        opcode: u8,
+        source: Operand,
+        encoding: Vec<u8>,
+    },
+}
+
+impl ControlOp {
+    /// Tries to decode a single-byte flag or processor control operation.
+    pub fn try_decode(bytes: &[u8]) -> DResult<Self> {
+        let op = match bytes[0] {
+            0xF8 => Self::Clc,
+            0xF9 => Self::Stc,
+            0xF5 => Self::Cmc,
+            0xFC => Self::Cld,
+            0xFD => Self::Std,
+            0xFA => Self::Cli,
+            0xFB => Self::Sti,
+            0x9B => Self::Wait,
+            byte => return Err(DecodeError::invalid_field(Field::OpCode, byte)),
+        };
+        Ok((op, 1))
+    }
+
+    /// Tries to decode an ESC operation.
+    pub fn try_decode_esc(bytes: &[u8]) -> DResult<Self> {
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let reg = Reg::parse_byte_mid(modrm);
+        let rm = RM::parse_byte(modrm);
+        let opcode = ((bytes[0] & 0b111) << 3) | u8::from(reg);
+
+        let (source, bytes_read) =
+            Operand::register_or_memory(true, &mode, rm.as_u8(), &bytes[2..])?;
+        let size = 2 + bytes_read;
+        let encoding = bytes[..size].to_vec();
+        Ok((
+            Self::Esc {
+                opcode,
+                source,
+                encoding,
+            },
+            size,
+        ))
+    }
+}
+
+impl fmt::Display for ControlOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Clc => write!(f, "clc"),
+            Self::Stc => write!(f, "stc"),
+            Self::Cmc => write!(f, "cmc"),
+            Self::Cld => write!(f, "cld"),
+            Self::Std => write!(f, "std"),
+            Self::Cli => write!(f, "cli"),
+            Self::Sti => write!(f, "sti"),
+            Self::Wait => write!(f, "wait"),
+            Self::Esc {
+                source, encoding, ..
+            } => write_esc(f, &[], source, encoding),
+        }
+    }
+}
+
+/// Writes an ESC operation as `db` data, so that it assembles to the same bytes.
+///
+/// The `prefixes` precede the segment override of the operand, if any.
+pub(crate) fn write_esc(
+    f: &mut fmt::Formatter<'_>,
+    prefixes: &[u8],
+    source: &Operand,
+    encoding: &[u8],
+) -> fmt::Result {
+    let segment = match source {
+        Operand::Memory(MemoryAddr {
+            segment: Some(segment),
+            ..
+        }) => Some(0x26 | (*segment as u8) << 3),
+        _ => None,
+    };
+    let bytes = prefixes.iter().chain(&segment).chain(encoding);
+    let bytes = bytes
+        .map(|byte| format!("0x{byte:02x}"))
+        .collect::<Vec<_>>();
+    write!(f, "db {}", bytes.join(", "))
+}
+
+/// A sign extension of the accumulator.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConvertOp {
+    /// Extends the sign of `AL` into `AH`.
+    Cbw,
+    /// Extends the sign of `AX` into `DX`.
+    Cwd,
+}
+
+impl ConvertOp {
+    /// Tries to decode a CBW or CWD operation, which are distinguished by the
+    /// least significant bit of the opcode.
+    pub fn try_decode(bytes: &[u8]) -> DResult<Self> {
+        match bytes[0] & 1 {
+            0 => Ok((Self::Cbw, 1)),
+            _ => Ok((Self::Cwd, 1)),
+        }
+    }
+}
+
+impl fmt::Display for ConvertOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cbw => write!(f, "cbw"),
+            Self::Cwd => write!(f, "cwd"),
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::fmt;
+
+use crate::code::{byte_at, DResult};
+
+/// A software interrupt or a return from an interrupt handler.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterruptOp {
+    /// Interrupt of the specified type.
+    Int(u8),
+    /// Breakpoint interrupt, of type 3.
+    Int3,
+    /// Interrupt of type 4 if the overflow flag is set.
+    Into,
+    /// Return from an interrupt handler.
+    Iret,
+}
+
+impl InterruptOp {
+    /// Tries to decode an INT, INT 3, INTO or IRET instruction, which are
+    /// distinguished by the two least significant bits of the opcode.
+    pub fn try_decode(bytes: &[u8]) -> DResult<Self> {
+        match bytes[0] & 0b11 {
+            0b00 => Ok((Self::Int3, 1)),
+            0b01 => Ok((Self::Int(byte_at(bytes, 1)?), 2)),
+            0b10 => Ok((Self::Into, 1)),
+            _ => Ok((Self::Iret, 1)),
+        }
+    }
+}
+
+impl fmt::Display for InterruptOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Int(vector) => write!(f, "int {vector}"),
+            Self::Int3 => write!(f, "int3"),
+            Self::Into => write!(f, "into"),
+            Self::Iret => write!(f, "iret"),
+        }
+    }
+}
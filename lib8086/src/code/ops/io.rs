@@ -0,0 +1,79 @@
+use std::fmt;
+
+use crate::code::{byte_at, get_bit, DResult, Register, Width};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IoOpType {
+    In,
+    Out,
+}
+
+/// `Port` represents the port operand of an IN or OUT instruction.
+///
+/// The port is either an immediate byte, which addresses ports 0 to 255, or
+/// the value held in the `DX` register, which addresses the full 64 KiB space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Port {
+    Immediate(u8),
+    DX,
+}
+
+impl fmt::Display for Port {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Immediate(port) => write!(f, "{port}"),
+            Self::DX => write!(f, "dx"),
+        }
+    }
+}
+
+/// A transfer between the accumulator and an I/O port.
+#[derive(Debug)]
+pub struct IoOp {
+    pub optype: IoOpType,
+    pub width: Width,
+    pub port: Port,
+}
+
+impl IoOp {
+    /// Tries to decode an IN or OUT instruction.
+    ///
+    /// The second least significant bit of the opcode distinguishes OUT from IN,
+    /// and the fourth one selects the `DX` port over an immediate port.
+    pub fn try_decode(bytes: &[u8]) -> DResult<Self> {
+        let width = Width::parse_byte(bytes[0], 0);
+        let optype = match get_bit(bytes[0], 1) {
+            false => IoOpType::In,
+            true => IoOpType::Out,
+        };
+        let (port, size) = match get_bit(bytes[0], 3) {
+            false => (Port::Immediate(byte_at(bytes, 1)?), 2),
+            true => (Port::DX, 1),
+        };
+        let op = Self {
+            optype,
+            width,
+            port,
+        };
+        Ok((op, size))
+    }
+
+    /// Returns the accumulator register the operation transfers data to or from.
+    #[inline]
+    pub fn accumulator(&self) -> Register {
+        match self.width {
+            Width::Byte => Register::AL,
+            Width::Word => Register::AX,
+        }
+    }
+}
+
+impl fmt::Display for IoOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let acc = self.accumulator();
+        match self.optype {
+            IoOpType::In => write!(f, "in {acc}, {}", self.port),
+            IoOpType::Out => write!(f, "out {}, {acc}", self.port),
+        }
+    }
+}
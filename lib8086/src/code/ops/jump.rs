@@ -1,5 +1,7 @@
 use std::fmt;
 
+use crate::code::{byte_at, get_bit, word_at, DResult, Mode, Operand, RM};
+
 macro_rules! create_cond_jump_ops {
     (
         $(
@@ -13,17 +15,11 @@ macro_rules! create_cond_jump_ops {
 
         impl fmt::Display for CondJumpOp {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-                let get_increment = |w: &mut fmt::Formatter<'_>, mnemonic: &str, inc: i8| -> fmt::Result {
-                    match inc + 2 {
-                        0 => write!(w, "{} $+0", mnemonic)?,
-                        1.. => write!(w, "{} $+{}+0", mnemonic, inc + 2)?,
-                        _ => write!(w, "{} ${}+0", mnemonic, inc + 2)?,
-                    }
-                    Ok(())
+                let (mnemonic, inc) = match self {
+                    $(Self::$opname(inc) => ($mnemonic, *inc),)+
                 };
-                match self {
-                    $(Self::$opname(inc) => get_increment(f, $mnemonic, *inc),)+
-                }
+                write!(f, "{mnemonic} ")?;
+                write_relative(f, inc as i32 + 2)
             }
         }
     }
@@ -51,3 +47,184 @@ create_cond_jump_ops! {
     (LoopEqual, "loopz");
     (LoopNEqual, "loopnz");
 }
+
+/// Writes a jump target relative to the start of the instruction, as `$+N+0`.
+fn write_relative(f: &mut fmt::Formatter<'_>, inc: i32) -> fmt::Result {
+    match inc {
+        0 => write!(f, "$+0"),
+        1.. => write!(f, "$+{inc}+0"),
+        _ => write!(f, "${inc}+0"),
+    }
+}
+
+/// The target of an unconditional jump or call.
+#[derive(Debug)]
+pub enum Target {
+    /// A target within -128 to +127 bytes of the next instruction.
+    Short(i8),
+    /// A target within the current code segment, relative to the next instruction.
+    Near(i16),
+    /// A target in any segment, specified directly.
+    Far { segment: u16, offset: u16 },
+    /// A target within the current code segment, held in a register or memory word.
+    Indirect(Operand),
+    /// A target in any segment, held in a memory doubleword as offset and segment.
+    IndirectFar(Operand),
+}
+
+impl Target {
+    /// Returns a mutable reference to the operand of an indirect target.
+    pub fn operand_mut(&mut self) -> Option<&mut Operand> {
+        match self {
+            Self::Indirect(operand) | Self::IndirectFar(operand) => Some(operand),
+            _ => None,
+        }
+    }
+
+    /// Decodes a direct target from the bytes following the opcode, returning the
+    /// target and the instruction size.
+    fn try_decode_direct(bytes: &[u8], optype: TargetType) -> DResult<Self> {
+        match optype {
+            TargetType::Short => Ok((Self::Short(byte_at(bytes, 1)? as i8), 2)),
+            TargetType::Near => Ok((Self::Near(i16::from_le_bytes(word_at(bytes, 1)?)), 3)),
+            TargetType::Far => {
+                let offset = u16::from_le_bytes(word_at(bytes, 1)?);
+                let segment = u16::from_le_bytes(word_at(bytes, 3)?);
+                Ok((Self::Far { segment, offset }, 5))
+            }
+        }
+    }
+
+    /// Decodes an indirect target from a Register/Memory operand, returning the
+    /// target and the instruction size.
+    fn try_decode_indirect(bytes: &[u8], far: bool) -> DResult<Self> {
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let rm = RM::parse_byte(modrm);
+        let (operand, bytes_read) =
+            Operand::register_or_memory(true, &mode, rm.as_u8(), &bytes[2..])?;
+        let target = match far {
+            true => Self::IndirectFar(operand),
+            false => Self::Indirect(operand),
+        };
+        Ok((target, 2 + bytes_read))
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Relative targets are offset by the size of the instruction: 2 bytes for
+        // short targets and 3 bytes for near ones.
+        match self {
+            Self::Short(inc) => {
+                write!(f, "short ")?;
+                write_relative(f, *inc as i32 + 2)
+            }
+            Self::Near(inc) => write_relative(f, *inc as i32 + 3),
+            Self::Far { segment, offset } => write!(f, "{segment}:{offset}"),
+            Self::Indirect(operand @ Operand::Memory(_)) => write!(f, "word {operand}"),
+            Self::Indirect(operand) => write!(f, "{operand}"),
+            Self::IndirectFar(operand) => write!(f, "far {operand}"),
+        }
+    }
+}
+
+/// The encodings of a direct jump or call target.
+pub enum TargetType {
+    Short,
+    Near,
+    Far,
+}
+
+#[derive(Debug)]
+pub struct JumpOp {
+    pub target: Target,
+}
+
+impl JumpOp {
+    /// Tries to decode a direct JMP instruction: short, near or far.
+    pub fn try_decode_direct(bytes: &[u8], optype: TargetType) -> DResult<Self> {
+        let (target, size) = Target::try_decode_direct(bytes, optype)?;
+        Ok((Self { target }, size))
+    }
+
+    /// Tries to decode an indirect JMP instruction through a Register/Memory operand.
+    pub fn try_decode_indirect(bytes: &[u8], far: bool) -> DResult<Self> {
+        let (target, size) = Target::try_decode_indirect(bytes, far)?;
+        Ok((Self { target }, size))
+    }
+}
+
+impl fmt::Display for JumpOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.target {
+            Target::Near(_) => write!(f, "jmp near {}", self.target),
+            _ => write!(f, "jmp {}", self.target),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct CallOp {
+    pub target: Target,
+}
+
+impl CallOp {
+    /// Tries to decode a direct CALL instruction: near or far.
+    pub fn try_decode_direct(bytes: &[u8], optype: TargetType) -> DResult<Self> {
+        let (target, size) = Target::try_decode_direct(bytes, optype)?;
+        Ok((Self { target }, size))
+    }
+
+    /// Tries to decode an indirect CALL instruction through a Register/Memory operand.
+    pub fn try_decode_indirect(bytes: &[u8], far: bool) -> DResult<Self> {
+        let (target, size) = Target::try_decode_indirect(bytes, far)?;
+        Ok((Self { target }, size))
+    }
+}
+
+impl fmt::Display for CallOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "call {}", self.target)
+    }
+}
+
+/// A return from a procedure.
+///
+/// Far returns also pop the code segment. The optional immediate is added to
+/// the stack pointer after the return address is popped, to discard parameters.
+#[derive(Debug)]
+pub struct RetOp {
+    pub far: bool,
+    pub pop: Option<u16>,
+}
+
+impl RetOp {
+    /// Tries to decode a RET or RETF instruction, with or without an immediate.
+    pub fn try_decode(bytes: &[u8]) -> DResult<Self> {
+        let far = get_bit(bytes[0], 3);
+        match get_bit(bytes[0], 0) {
+            true => Ok((Self { far, pop: None }, 1)),
+            false => {
+                let pop = u16::from_le_bytes(word_at(bytes, 1)?);
+                Ok((
+                    Self {
+                        far,
+                        pop: Some(pop),
+                    },
+                    3,
+                ))
+            }
+        }
+    }
+}
+
+impl fmt::Display for RetOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mnemonic = if self.far { "retf" } else { "ret" };
+        match self.pop {
+            Some(pop) => write!(f, "{mnemonic} {pop}"),
+            None => write!(f, "{mnemonic}"),
+        }
+    }
+}
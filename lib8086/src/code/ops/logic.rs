@@ -0,0 +1,210 @@
+use std::fmt;
+
+use crate::code::fields::*;
+use crate::code::operand::{get_immediate_prefix, get_operands, get_unary_prefix, Operand};
+use crate::code::{byte_at, word_at, DResult, DecodeError, Field, Register};
+use crate::value::Value;
+
+pub enum LogicOpType {
+    And = 0b100,
+    Or = 0b001,
+    Xor = 0b110,
+    Test = 0b000,
+}
+
+impl LogicOpType {
+    /// Parses the REG field of an Immediate - Register/Memory instruction
+    /// and extracts the logical operation type.
+    pub fn try_parse_byte(byte: u8) -> Result<Self, DecodeError> {
+        match (byte >> 3) & 0b111 {
+            0b100 => Ok(Self::And),
+            0b001 => Ok(Self::Or),
+            0b110 => Ok(Self::Xor),
+            reg => Err(DecodeError::invalid_field(Field::Reg, reg)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LogicOp {
+    And {
+        source: Operand,
+        destination: Operand,
+    },
+    Or {
+        source: Operand,
+        destination: Operand,
+    },
+    Xor {
+        source: Operand,
+        destination: Operand,
+    },
+    Test {
+        source: Operand,
+        destination: Operand,
+    },
+    Not {
+        destination: Operand,
+        width: Width,
+    },
+}
+
+impl LogicOp {
+    fn new(source: Operand, destination: Operand, optype: LogicOpType) -> Self {
+        match optype {
+            LogicOpType::And => Self::And {
+                source,
+                destination,
+            },
+            LogicOpType::Or => Self::Or {
+                source,
+                destination,
+            },
+            LogicOpType::Xor => Self::Xor {
+                source,
+                destination,
+            },
+            LogicOpType::Test => Self::Test {
+                source,
+                destination,
+            },
+        }
+    }
+
+    /// Returns mutable references to the source operand, if any, and the
+    /// destination operand.
+    pub fn operands_mut(&mut self) -> (Option<&mut Operand>, &mut Operand) {
+        match self {
+            Self::And {
+                source,
+                destination,
+            }
+            | Self::Or {
+                source,
+                destination,
+            }
+            | Self::Xor {
+                source,
+                destination,
+            }
+            | Self::Test {
+                source,
+                destination,
+            } => (Some(source), destination),
+            Self::Not { destination, .. } => (None, destination),
+        }
+    }
+
+    /// Tries to decode a Register/Memory - Register logical operation.
+    /// These include AND, OR, XOR and TEST.
+    ///
+    /// TEST has no direction bit; its register operand is always the source.
+    pub fn try_decode_rm_reg(bytes: &[u8], optype: LogicOpType) -> DResult<Self> {
+        let width = Width::parse_byte(bytes[0], 0);
+        let direction = match optype {
+            LogicOpType::Test => Direction::Source,
+            _ => Direction::parse_byte(bytes[0]),
+        };
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let reg = Reg::parse_byte_mid(modrm);
+        let rm = RM::parse_byte(modrm);
+
+        let ((source, dest), bytes_read) =
+            get_operands(mode, direction, width, reg, rm, &bytes[2..])?;
+        Ok((Self::new(source, dest, optype), bytes_read + 2))
+    }
+
+    /// Tries to decode an Immediate - Register/Memory logical operation.
+    /// These include AND, OR and XOR, which share their opcode with the
+    /// arithmetic operations, and TEST.
+    pub fn try_decode_im_rm(bytes: &[u8], optype: LogicOpType) -> DResult<Self> {
+        let width = Width::parse_byte(bytes[0], 0);
+        // TEST has its own opcode and no sign-extension bit.
+        let sign = match optype {
+            LogicOpType::Test => Sign::NoExtention,
+            _ => Sign::parse_byte(bytes[0]),
+        };
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let rm = RM::parse_byte(modrm);
+
+        let (dest, bytes_read) =
+            Operand::register_or_memory(width.as_bool(), &mode, rm.as_u8(), &bytes[2..])?;
+        let rest = &bytes[2 + bytes_read..];
+
+        match (width, sign) {
+            (Width::Byte, _) => {
+                let source = Operand::immediate(Value::byte(byte_at(rest, 0)?));
+                Ok((Self::new(source, dest, optype), 3 + bytes_read))
+            }
+            (Width::Word, Sign::NoExtention) => {
+                let source = Operand::immediate(Value::word(word_at(rest, 0)?));
+                Ok((Self::new(source, dest, optype), 4 + bytes_read))
+            }
+            (Width::Word, Sign::Extend) => {
+                let val = (byte_at(rest, 0)? as i8) as i16;
+                let source = Operand::immediate(Value::Word(val));
+                Ok((Self::new(source, dest, optype), 3 + bytes_read))
+            }
+        }
+    }
+
+    /// Tries to decode an Immediate - Accumulator logical operation.
+    /// These include AND, OR, XOR and TEST.
+    pub fn try_decode_im_acc(bytes: &[u8], optype: LogicOpType) -> DResult<Self> {
+        match Width::parse_byte(bytes[0], 0) {
+            Width::Byte => {
+                let dest = Operand::Register(Register::AL);
+                let source = Operand::immediate(Value::byte(byte_at(bytes, 1)?));
+                Ok((Self::new(source, dest, optype), 2))
+            }
+            Width::Word => {
+                let dest = Operand::Register(Register::AX);
+                let source = Operand::immediate(Value::word(word_at(bytes, 1)?));
+                Ok((Self::new(source, dest, optype), 3))
+            }
+        }
+    }
+
+    /// Tries to decode a Register/Memory NOT operation.
+    pub fn try_decode_not(bytes: &[u8]) -> DResult<Self> {
+        let width = Width::parse_byte(bytes[0], 0);
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let rm = RM::parse_byte(modrm);
+
+        let (destination, bytes_read) =
+            Operand::register_or_memory(width.as_bool(), &mode, rm.as_u8(), &bytes[2..])?;
+        Ok((Self::Not { destination, width }, 2 + bytes_read))
+    }
+}
+
+impl fmt::Display for LogicOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mnemonic, source, destination) = match self {
+            Self::And {
+                source,
+                destination,
+            } => ("and", source, destination),
+            Self::Or {
+                source,
+                destination,
+            } => ("or", source, destination),
+            Self::Xor {
+                source,
+                destination,
+            } => ("xor", source, destination),
+            Self::Test {
+                source,
+                destination,
+            } => ("test", source, destination),
+            Self::Not { destination, width } => {
+                let prefix = get_unary_prefix(destination, width);
+                return write!(f, "not {prefix}{destination}");
+            }
+        };
+        let prefix = get_immediate_prefix(source, destination);
+        write!(f, "{mnemonic} {prefix}{destination}, {source}")
+    }
+}
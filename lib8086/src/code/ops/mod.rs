@@ -1,5 +1,5 @@
 pub mod codes;
-pub use codes::OpCode;
+pub use codes::{GroupOpCode, OpCode};
 
 pub mod mov;
 pub use mov::MovOp;
@@ -8,7 +8,34 @@ pub mod pushpop;
 pub use pushpop::{PopOp, PushOp};
 
 pub mod num;
-pub use num::{NumOp, NumOpType};
+pub use num::{NumOp, NumOpType, UnaryOpType};
+
+pub mod logic;
+pub use logic::{LogicOp, LogicOpType};
+
+pub mod shift;
+pub use shift::{ShiftCount, ShiftOp, ShiftOpType};
+
+pub mod muldiv;
+pub use muldiv::{MulDivOp, MulDivOpType};
+
+pub mod string;
+pub use string::{RepPrefix, StringOp, StringOpType};
 
 pub mod jump;
-pub use jump::CondJumpOp;
+pub use jump::{CallOp, CondJumpOp, JumpOp, RetOp, Target, TargetType};
+
+pub mod interrupt;
+pub use interrupt::InterruptOp;
+
+pub mod io;
+pub use io::{IoOp, IoOpType, Port};
+
+pub mod adjust;
+pub use adjust::AdjustOp;
+
+pub mod control;
+pub use control::{ControlOp, ConvertOp};
+
+pub mod transfer;
+pub use transfer::{LoadAddrOpType, TransferOp};
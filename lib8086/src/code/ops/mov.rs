@@ -2,7 +2,8 @@ use std::fmt;
 
 use crate::code::fields::*;
 use crate::code::{
-    get_bit, get_operands, DResult, EffectiveAddr, Operand, Register, SegmentRegister,
+    byte_at, get_bit, get_operands, word_at, DResult, DecodeError, EffectiveAddr, Field,
+    MemoryAddr, Operand, Register, SegmentRegister,
 };
 use crate::value::Value;
 
@@ -25,9 +26,10 @@ impl MovOp {
     pub fn try_parse_reg_rm(bytes: &[u8]) -> DResult<Self> {
         let direction = Direction::parse_byte(bytes[0]);
         let width = Width::parse_byte(bytes[0], 0);
-        let mode = Mode::try_parse_byte(bytes[1])?;
-        let reg = Reg::parse_byte_mid(bytes[1]);
-        let rm = RM::parse_byte(bytes[1]);
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let reg = Reg::parse_byte_mid(modrm);
+        let rm = RM::parse_byte(modrm);
 
         let ((source, dest), bytes_read) =
             get_operands(mode, direction, width, reg, rm, &bytes[2..])?;
@@ -37,9 +39,13 @@ impl MovOp {
     /// Parse an Immediate - Register/Memory MOV instruction.
     pub fn try_parse_im_rm(bytes: &[u8]) -> DResult<Self> {
         let width = Width::parse_byte(bytes[0], 0);
-        let mode = Mode::try_parse_byte(bytes[1])?;
-        assert_eq!((bytes[1] >> 3) & 0b111, 0b000u8);
-        let rm = RM::parse_byte(bytes[1]);
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let reg = u8::from(Reg::parse_byte_mid(modrm));
+        if reg != 0b000 {
+            return Err(DecodeError::invalid_field(Field::Reg, reg));
+        }
+        let rm = RM::parse_byte(modrm);
 
         let (dest, bytes_read) =
             Operand::register_or_memory(width.as_bool(), &mode, rm.as_u8(), &bytes[2..])?;
@@ -47,11 +53,11 @@ impl MovOp {
 
         match width {
             Width::Byte => {
-                let source = Operand::immediate(Value::byte(remaining[0]));
+                let source = Operand::immediate(Value::byte(byte_at(remaining, 0)?));
                 Ok((MovOp::new(source, dest), 3 + bytes_read))
             }
             Width::Word => {
-                let source = Operand::immediate(Value::word([remaining[0], remaining[1]]));
+                let source = Operand::immediate(Value::word(word_at(remaining, 0)?));
                 Ok((MovOp::new(source, dest), 4 + bytes_read))
             }
         }
@@ -63,8 +69,8 @@ impl MovOp {
         let n_bytes = width.n_bytes();
         let reg = Reg::parse_byte_low(bytes[0]);
         let value = match width {
-            Width::Byte => Value::byte(bytes[1]),
-            Width::Word => Value::word([bytes[1], bytes[2]]),
+            Width::Byte => Value::byte(byte_at(bytes, 1)?),
+            Width::Word => Value::word(word_at(bytes, 1)?),
         };
 
         let source = Operand::immediate(value);
@@ -74,8 +80,8 @@ impl MovOp {
 
     /// Decodes a Memory to Accumulator MOV instruction.
     pub fn try_decode_mem_acc(bytes: &[u8]) -> DResult<Self> {
-        let addr = EffectiveAddr::Direct(u16::from_le_bytes([bytes[1], bytes[2]]));
-        let mem = Operand::Memory(addr);
+        let addr = EffectiveAddr::Direct(u16::from_le_bytes(word_at(bytes, 1)?));
+        let mem = Operand::Memory(MemoryAddr::new(addr));
         let acc = match Width::parse_byte(bytes[0], 0) {
             Width::Byte => Operand::Register(Register::AL),
             Width::Word => Operand::Register(Register::AX),
@@ -88,10 +94,11 @@ impl MovOp {
     }
 
     pub fn try_decode_rm_segreg(bytes: &[u8]) -> DResult<Self> {
-        let mode = Mode::try_parse_byte(bytes[1])?;
-        let sr = (bytes[1] >> 3) & 0b11;
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let sr = (modrm >> 3) & 0b11;
         let segreg = SegmentRegister::try_from(sr)?;
-        let rm = RM::parse_byte(bytes[1]).as_u8();
+        let rm = RM::parse_byte(modrm).as_u8();
 
         let (rm_operand, bytes_read) = Operand::register_or_memory(true, &mode, rm, &bytes[2..])?;
         let segreg_operand = Operand::SegmentRegister(segreg);
@@ -0,0 +1,77 @@
+use std::fmt;
+
+use crate::code::fields::*;
+use crate::code::operand::get_unary_prefix;
+use crate::code::{byte_at, DResult, DecodeError, Field, Operand};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MulDivOpType {
+    Mul = 0b100,
+    Imul = 0b101,
+    Div = 0b110,
+    Idiv = 0b111,
+}
+
+impl MulDivOpType {
+    /// Parses the REG field of a multiply/divide instruction and extracts
+    /// the operation type.
+    fn try_parse_byte(byte: u8) -> Result<Self, DecodeError> {
+        match (byte >> 3) & 0b111 {
+            0b100 => Ok(Self::Mul),
+            0b101 => Ok(Self::Imul),
+            0b110 => Ok(Self::Div),
+            0b111 => Ok(Self::Idiv),
+            reg => Err(DecodeError::invalid_field(Field::Reg, reg)),
+        }
+    }
+}
+
+impl fmt::Display for MulDivOpType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Mul => "mul",
+            Self::Imul => "imul",
+            Self::Div => "div",
+            Self::Idiv => "idiv",
+        })
+    }
+}
+
+/// A multiply or divide operation.
+///
+/// The other operand and the destination are implied by the width: byte
+/// operations use `AL` and `AX`, while word operations use `AX` and `DX:AX`.
+#[derive(Debug)]
+pub struct MulDivOp {
+    pub optype: MulDivOpType,
+    pub source: Operand,
+    pub width: Width,
+}
+
+impl MulDivOp {
+    /// Tries to decode a multiply or divide operation on a Register/Memory operand.
+    /// These include MUL, IMUL, DIV and IDIV.
+    pub fn try_decode(bytes: &[u8]) -> DResult<Self> {
+        let width = Width::parse_byte(bytes[0], 0);
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let optype = MulDivOpType::try_parse_byte(modrm)?;
+        let rm = RM::parse_byte(modrm);
+
+        let (source, bytes_read) =
+            Operand::register_or_memory(width.as_bool(), &mode, rm.as_u8(), &bytes[2..])?;
+        let op = Self {
+            optype,
+            source,
+            width,
+        };
+        Ok((op, 2 + bytes_read))
+    }
+}
+
+impl fmt::Display for MulDivOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = get_unary_prefix(&self.source, &self.width);
+        write!(f, "{} {prefix}{}", self.optype, self.source)
+    }
+}
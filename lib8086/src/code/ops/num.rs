@@ -1,8 +1,8 @@
 use std::fmt;
 
 use crate::code::fields::*;
-use crate::code::operand::{get_operands, get_prefix, Operand};
-use crate::code::{DResult, DecodeError, Register};
+use crate::code::operand::{get_operands, get_prefix, get_unary_prefix, Operand};
+use crate::code::{byte_at, word_at, DResult, DecodeError, Field, Register};
 use crate::value::Value;
 
 pub enum NumOpType {
@@ -21,11 +21,18 @@ impl NumOpType {
             0b101 => Ok(Self::Sub),
             0b011 => Ok(Self::Sbb),
             0b111 => Ok(Self::Cmp),
-            _ => Err(DecodeError::NumType),
+            reg => Err(DecodeError::invalid_field(Field::Reg, reg)),
         }
     }
 }
 
+/// The arithmetic operations that act on a single operand.
+pub enum UnaryOpType {
+    Inc,
+    Dec,
+    Neg,
+}
+
 #[derive(Debug)]
 pub enum NumOp {
     Add {
@@ -48,6 +55,18 @@ pub enum NumOp {
         source: Operand,
         destination: Operand,
     },
+    Inc {
+        destination: Operand,
+        width: Width,
+    },
+    Dec {
+        destination: Operand,
+        width: Width,
+    },
+    Neg {
+        destination: Operand,
+        width: Width,
+    },
 }
 
 impl NumOp {
@@ -76,14 +95,53 @@ impl NumOp {
         }
     }
 
+    fn new_unary(destination: Operand, width: Width, optype: UnaryOpType) -> Self {
+        match optype {
+            UnaryOpType::Inc => Self::Inc { destination, width },
+            UnaryOpType::Dec => Self::Dec { destination, width },
+            UnaryOpType::Neg => Self::Neg { destination, width },
+        }
+    }
+
+    /// Returns mutable references to the source operand, if any, and the
+    /// destination operand.
+    pub fn operands_mut(&mut self) -> (Option<&mut Operand>, &mut Operand) {
+        match self {
+            Self::Add {
+                source,
+                destination,
+            }
+            | Self::Adc {
+                source,
+                destination,
+            }
+            | Self::Sub {
+                source,
+                destination,
+            }
+            | Self::Sbb {
+                source,
+                destination,
+            }
+            | Self::Cmp {
+                source,
+                destination,
+            } => (Some(source), destination),
+            Self::Inc { destination, .. }
+            | Self::Dec { destination, .. }
+            | Self::Neg { destination, .. } => (None, destination),
+        }
+    }
+
     /// Tries to decode a Register/Memory - Register arithmetic operation.
     /// These include ADD, ADC, SUB, SBB, and CMP.
     pub fn try_decode_rm_reg(bytes: &[u8], optype: NumOpType) -> DResult<Self> {
         let width = Width::parse_byte(bytes[0], 0);
         let direction = Direction::parse_byte(bytes[0]);
-        let mode = Mode::try_parse_byte(bytes[1])?;
-        let reg = Reg::parse_byte_mid(bytes[1]);
-        let rm = RM::parse_byte(bytes[1]);
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let reg = Reg::parse_byte_mid(modrm);
+        let rm = RM::parse_byte(modrm);
 
         let ((source, dest), bytes_read) =
             get_operands(mode, direction, width, reg, rm, &bytes[2..])?;
@@ -95,9 +153,10 @@ impl NumOp {
     pub fn try_decode_im_rm(bytes: &[u8]) -> DResult<Self> {
         let width = Width::parse_byte(bytes[0], 0);
         let sign = Sign::parse_byte(bytes[0]);
-        let mode = Mode::try_parse_byte(bytes[1])?;
-        let optype = NumOpType::try_parse_byte(bytes[1])?;
-        let rm = RM::parse_byte(bytes[1]);
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let optype = NumOpType::try_parse_byte(modrm)?;
+        let rm = RM::parse_byte(modrm);
 
         let (dest, bytes_read) =
             Operand::register_or_memory(width.as_bool(), &mode, rm.as_u8(), &bytes[2..])?;
@@ -105,19 +164,19 @@ impl NumOp {
 
         match (width, sign) {
             (Width::Byte, Sign::NoExtention) => {
-                let source = Operand::immediate(Value::byte(rest[0]));
+                let source = Operand::immediate(Value::byte(byte_at(rest, 0)?));
                 Ok((Self::new(source, dest, optype), 3 + bytes_read))
             }
             (Width::Word, Sign::NoExtention) => {
-                let source = Operand::immediate(Value::word([rest[0], rest[1]]));
+                let source = Operand::immediate(Value::word(word_at(rest, 0)?));
                 Ok((Self::new(source, dest, optype), 4 + bytes_read))
             }
             (Width::Byte, Sign::Extend) => {
-                let source = Operand::immediate(Value::byte(rest[0]));
+                let source = Operand::immediate(Value::byte(byte_at(rest, 0)?));
                 Ok((Self::new(source, dest, optype), 3 + bytes_read))
             }
             (Width::Word, Sign::Extend) => {
-                let val = (rest[0] as i8) as i16;
+                let val = (byte_at(rest, 0)? as i8) as i16;
                 let source = Operand::immediate(Value::Word(val));
                 Ok((Self::new(source, dest, optype), 3 + bytes_read))
             }
@@ -130,17 +189,37 @@ impl NumOp {
         match Width::parse_byte(bytes[0], 0) {
             Width::Byte => {
                 let dest = Operand::Register(Register::AL);
-                let source = Operand::immediate(Value::byte(bytes[1]));
+                let source = Operand::immediate(Value::byte(byte_at(bytes, 1)?));
                 Ok((Self::new(source, dest, optype), 2))
             }
             Width::Word => {
                 let dest = Operand::Register(Register::AX);
-                let source = Operand::immediate(Value::word([bytes[1], bytes[2]]));
+                let source = Operand::immediate(Value::word(word_at(bytes, 1)?));
                 Ok((Self::new(source, dest, optype), 3))
                 // Maybe add Value::Signed(i16)
             }
         }
     }
+
+    /// Tries to decode a single-byte Register INC or DEC operation.
+    pub fn try_decode_reg(bytes: &[u8], optype: UnaryOpType) -> DResult<Self> {
+        let reg = Reg::parse_byte_low(bytes[0]);
+        let destination = Operand::register(reg.into(), true);
+        Ok((Self::new_unary(destination, Width::Word, optype), 1))
+    }
+
+    /// Tries to decode a Register/Memory single-operand arithmetic operation.
+    /// These include INC, DEC and NEG.
+    pub fn try_decode_rm(bytes: &[u8], optype: UnaryOpType) -> DResult<Self> {
+        let width = Width::parse_byte(bytes[0], 0);
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let rm = RM::parse_byte(modrm);
+
+        let (destination, bytes_read) =
+            Operand::register_or_memory(width.as_bool(), &mode, rm.as_u8(), &bytes[2..])?;
+        Ok((Self::new_unary(destination, width, optype), 2 + bytes_read))
+    }
 }
 
 impl fmt::Display for NumOp {
@@ -181,6 +260,27 @@ impl fmt::Display for NumOp {
                 let prefix = get_prefix(source, destination);
                 write!(f, "cmp {prefix}{destination}, {source}")
             }
+            Self::Inc { destination, width } => {
+                write!(
+                    f,
+                    "inc {}{destination}",
+                    get_unary_prefix(destination, width)
+                )
+            }
+            Self::Dec { destination, width } => {
+                write!(
+                    f,
+                    "dec {}{destination}",
+                    get_unary_prefix(destination, width)
+                )
+            }
+            Self::Neg { destination, width } => {
+                write!(
+                    f,
+                    "neg {}{destination}",
+                    get_unary_prefix(destination, width)
+                )
+            }
         }
     }
 }
@@ -1,6 +1,6 @@
 use std::fmt;
 
-use crate::code::{DResult, Mode, Operand, Reg, Register, SegmentRegister, RM, SR};
+use crate::code::{byte_at, DResult, Mode, Operand, Reg, Register, SegmentRegister, RM, SR};
 
 macro_rules! create_push_pop_op {
     (
@@ -17,10 +17,11 @@ macro_rules! create_push_pop_op {
             impl $op_name {
 
                 pub fn try_decode_rm(bytes: &[u8]) -> DResult<Self> {
-                    let mode = Mode::try_parse_byte(bytes[1])?;
-                    let rm = RM::parse_byte(bytes[1]);
-                    let (operand, rest) = Operand::register_or_memory(true, &mode, rm.as_u8(), &bytes[2..])?;
-                    Ok((Self { operand }, rest))
+                    let modrm = byte_at(bytes, 1)?;
+                    let mode = Mode::try_parse_byte(modrm)?;
+                    let rm = RM::parse_byte(modrm);
+                    let (operand, bytes_read) = Operand::register_or_memory(true, &mode, rm.as_u8(), &bytes[2..])?;
+                    Ok((Self { operand }, 2 + bytes_read))
                 }
 
                 pub fn try_decode_reg(bytes: &[u8]) -> DResult<Self> {
@@ -32,7 +33,7 @@ macro_rules! create_push_pop_op {
 
                 pub fn try_decode_seg_reg(bytes: &[u8]) -> DResult<Self> {
                     let sr = SR::parse_byte(bytes[0]);
-                    let segreg = SegmentRegister::try_from(sr.as_u8()).unwrap();
+                    let segreg = SegmentRegister::try_from(sr.as_u8())?;
                     let operand = Operand::SegmentRegister(segreg);
                     Ok((Self { operand }, 1))
                 }
@@ -0,0 +1,126 @@
+use std::fmt;
+
+use crate::code::fields::*;
+use crate::code::operand::get_unary_prefix;
+use crate::code::{byte_at, get_bit, DResult, DecodeError, Field, Operand};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShiftOpType {
+    Rol = 0b000,
+    Ror = 0b001,
+    Rcl = 0b010,
+    Rcr = 0b011,
+    Shl = 0b100,
+    Shr = 0b101,
+    Sar = 0b111,
+}
+
+impl ShiftOpType {
+    /// Parses the REG field of a shift/rotate instruction and extracts
+    /// the operation type.
+    fn try_parse_byte(byte: u8) -> Result<Self, DecodeError> {
+        match (byte >> 3) & 0b111 {
+            0b000 => Ok(Self::Rol),
+            0b001 => Ok(Self::Ror),
+            0b010 => Ok(Self::Rcl),
+            0b011 => Ok(Self::Rcr),
+            0b100 => Ok(Self::Shl),
+            0b101 => Ok(Self::Shr),
+            0b111 => Ok(Self::Sar),
+            reg => Err(DecodeError::invalid_field(Field::Reg, reg)),
+        }
+    }
+
+    /// Returns whether the operation is a rotation.
+    #[inline]
+    pub fn is_rotate(&self) -> bool {
+        matches!(self, Self::Rol | Self::Ror | Self::Rcl | Self::Rcr)
+    }
+}
+
+impl fmt::Display for ShiftOpType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Rol => "rol",
+            Self::Ror => "ror",
+            Self::Rcl => "rcl",
+            Self::Rcr => "rcr",
+            Self::Shl => "shl",
+            Self::Shr => "shr",
+            Self::Sar => "sar",
+        })
+    }
+}
+
+/// `ShiftCount` represents the `V` field of a shift/rotate instruction.
+///
+/// If `V = 0` the count is one; otherwise, the count is held in the `CL`
+/// register.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShiftCount {
+    One,
+    CL,
+}
+
+impl ShiftCount {
+    /// Parses a byte and extracts the count field from the second least
+    /// significant bit.
+    #[inline]
+    pub fn parse_byte(byte: u8) -> Self {
+        match get_bit(byte, 1) {
+            false => Self::One,
+            true => Self::CL,
+        }
+    }
+}
+
+impl fmt::Display for ShiftCount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::One => write!(f, "1"),
+            Self::CL => write!(f, "cl"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ShiftOp {
+    pub optype: ShiftOpType,
+    pub destination: Operand,
+    pub count: ShiftCount,
+    pub width: Width,
+}
+
+impl ShiftOp {
+    /// Tries to decode a shift or rotate operation on a Register/Memory operand.
+    /// These include SHL/SAL, SHR, SAR, ROL, ROR, RCL and RCR.
+    pub fn try_decode(bytes: &[u8]) -> DResult<Self> {
+        let count = ShiftCount::parse_byte(bytes[0]);
+        let width = Width::parse_byte(bytes[0], 0);
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let optype = ShiftOpType::try_parse_byte(modrm)?;
+        let rm = RM::parse_byte(modrm);
+
+        let (destination, bytes_read) =
+            Operand::register_or_memory(width.as_bool(), &mode, rm.as_u8(), &bytes[2..])?;
+        let op = Self {
+            optype,
+            destination,
+            count,
+            width,
+        };
+        Ok((op, 2 + bytes_read))
+    }
+}
+
+impl fmt::Display for ShiftOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = get_unary_prefix(&self.destination, &self.width);
+        write!(
+            f,
+            "{} {prefix}{}, {}",
+            self.optype, self.destination, self.count
+        )
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use crate::code::{get_bit, DResult, SegmentRegister, Width};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StringOpType {
+    Movs,
+    Cmps,
+    Scas,
+    Lods,
+    Stos,
+}
+
+impl StringOpType {
+    /// Returns whether the operation compares its operands, in which case a
+    /// repeat prefix also checks the zero flag.
+    #[inline]
+    pub fn is_compare(&self) -> bool {
+        matches!(self, Self::Cmps | Self::Scas)
+    }
+}
+
+/// `RepPrefix` represents the repeat prefix of a string instruction.
+///
+/// The prefix repeats the instruction while `CX` is non-zero. For CMPS and SCAS,
+/// `Rep` (REPE/REPZ) also stops when the zero flag is cleared, and `Repne`
+/// (REPNE/REPNZ) stops when it is set.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RepPrefix {
+    Rep,
+    Repne,
+}
+
+impl RepPrefix {
+    /// Parses a repeat prefix byte, whose least significant bit distinguishes
+    /// between REP and REPNE.
+    #[inline]
+    pub fn parse_byte(byte: u8) -> Self {
+        match get_bit(byte, 0) {
+            true => Self::Rep,
+            false => Self::Repne,
+        }
+    }
+}
+
+/// A string operation on the byte or word at `DS:SI` and/or `ES:DI`.
+///
+/// A segment override prefix replaces the `DS` segment of the source operand;
+/// the destination operand is always in the extra segment.
+#[derive(Debug)]
+pub struct StringOp {
+    pub optype: StringOpType,
+    pub width: Width,
+    pub rep: Option<RepPrefix>,
+    pub segment: Option<SegmentRegister>,
+}
+
+impl StringOp {
+    /// Tries to decode a string operation without a repeat prefix.
+    pub fn try_decode(bytes: &[u8], optype: StringOpType) -> DResult<Self> {
+        let width = Width::parse_byte(bytes[0], 0);
+        let op = Self {
+            optype,
+            width,
+            rep: None,
+            segment: None,
+        };
+        Ok((op, 1))
+    }
+
+    /// Returns whether the operation reads its source operand at `DS:SI`, in which
+    /// case its segment can be overridden.
+    #[inline]
+    pub fn uses_source(&self) -> bool {
+        matches!(
+            self.optype,
+            StringOpType::Movs | StringOpType::Cmps | StringOpType::Lods
+        )
+    }
+
+    /// Returns the operation with the specified repeat prefix.
+    #[inline]
+    pub fn with_rep(self, rep: RepPrefix) -> Self {
+        Self {
+            rep: Some(rep),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for StringOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.rep, self.optype.is_compare()) {
+            (Some(RepPrefix::Rep), true) => write!(f, "repe ")?,
+            (Some(RepPrefix::Rep), false) => write!(f, "rep ")?,
+            (Some(RepPrefix::Repne), _) => write!(f, "repne ")?,
+            (None, _) => {}
+        }
+        if let Some(segment) = self.segment {
+            write!(f, "{segment} ")?;
+        }
+        let mnemonic = match self.optype {
+            StringOpType::Movs => "movs",
+            StringOpType::Cmps => "cmps",
+            StringOpType::Scas => "scas",
+            StringOpType::Lods => "lods",
+            StringOpType::Stos => "stos",
+        };
+        let suffix = match self.width {
+            Width::Byte => 'b',
+            Width::Word => 'w',
+        };
+        write!(f, "{mnemonic}{suffix}")
+    }
+}
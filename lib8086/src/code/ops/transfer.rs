@@ -0,0 +1,152 @@
+use std::fmt;
+
+use crate::code::fields::*;
+use crate::code::operand::get_operands;
+use crate::code::{byte_at, DResult, DecodeError, Field, Operand, Register, SegmentRegister};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LoadAddrOpType {
+    Lea,
+    Lds,
+    Les,
+}
+
+#[derive(Debug)]
+pub enum TransferOp {
+    /// Exchanges the contents of the two operands.
+    Xchg {
+        source: Operand,
+        destination: Operand,
+    },
+    /// `XCHG AX, AX`, which has a dedicated single-byte encoding and does nothing.
+    Nop,
+    /// Replaces `AL` with the byte at `DS:[BX + AL]`, unless the segment is
+    /// overridden.
+    Xlat { segment: Option<SegmentRegister> },
+    /// Loads the offset of a memory operand into a register (LEA), or a far
+    /// pointer into a register and the `DS` (LDS) or `ES` (LES) register.
+    LoadAddr {
+        optype: LoadAddrOpType,
+        source: Operand,
+        destination: Operand,
+    },
+    /// Loads the low byte of the FLAGS word into `AH`.
+    Lahf,
+    /// Stores `AH` into the low byte of the FLAGS word.
+    Sahf,
+    /// Pushes the FLAGS word onto the stack.
+    Pushf,
+    /// Pops the FLAGS word from the stack.
+    Popf,
+}
+
+impl TransferOp {
+    /// Tries to decode a Register/Memory - Register XCHG instruction.
+    pub fn try_decode_xchg_rm_reg(bytes: &[u8]) -> DResult<Self> {
+        let width = Width::parse_byte(bytes[0], 0);
+        let direction = Direction::parse_byte(bytes[0]);
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        let reg = Reg::parse_byte_mid(modrm);
+        let rm = RM::parse_byte(modrm);
+
+        let ((source, destination), bytes_read) =
+            get_operands(mode, direction, width, reg, rm, &bytes[2..])?;
+        let op = Self::Xchg {
+            source,
+            destination,
+        };
+        Ok((op, 2 + bytes_read))
+    }
+
+    /// Tries to decode a Register - Accumulator XCHG instruction, or NOP if the
+    /// register is the accumulator itself.
+    pub fn try_decode_xchg_acc(bytes: &[u8]) -> DResult<Self> {
+        let reg = Reg::parse_byte_low(bytes[0]);
+        let register = Register::from(reg.into(), true);
+        if register == Register::AX {
+            return Ok((Self::Nop, 1));
+        }
+        let op = Self::Xchg {
+            source: Operand::Register(register),
+            destination: Operand::Register(Register::AX),
+        };
+        Ok((op, 1))
+    }
+
+    /// Tries to decode an LEA, LDS or LES instruction.
+    ///
+    /// The source operand must be in memory.
+    pub fn try_decode_load_addr(bytes: &[u8], optype: LoadAddrOpType) -> DResult<Self> {
+        let modrm = byte_at(bytes, 1)?;
+        let mode = Mode::try_parse_byte(modrm)?;
+        if mode == Mode::Register {
+            return Err(DecodeError::invalid_field(Field::Mod, 0b11));
+        }
+        let reg = Reg::parse_byte_mid(modrm);
+        let rm = RM::parse_byte(modrm);
+
+        let ((source, destination), bytes_read) = get_operands(
+            mode,
+            Direction::Destination,
+            Width::Word,
+            reg,
+            rm,
+            &bytes[2..],
+        )?;
+        let op = Self::LoadAddr {
+            optype,
+            source,
+            destination,
+        };
+        Ok((op, 2 + bytes_read))
+    }
+
+    /// Returns mutable references to the operands of the operation, if any.
+    pub fn operands_mut(&mut self) -> (Option<&mut Operand>, Option<&mut Operand>) {
+        match self {
+            Self::Xchg {
+                source,
+                destination,
+            }
+            | Self::LoadAddr {
+                source,
+                destination,
+                ..
+            } => (Some(source), Some(destination)),
+            _ => (None, None),
+        }
+    }
+}
+
+impl fmt::Display for TransferOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Xchg {
+                source,
+                destination,
+            } => write!(f, "xchg {destination}, {source}"),
+            Self::Nop => write!(f, "nop"),
+            Self::Xlat {
+                segment: Some(segment),
+            } => write!(f, "{segment} xlatb"),
+            Self::Xlat { segment: None } => write!(f, "xlatb"),
+            Self::LoadAddr {
+                optype,
+                source,
+                destination,
+            } => {
+                let mnemonic = match optype {
+                    LoadAddrOpType::Lea => "lea",
+                    LoadAddrOpType::Lds => "lds",
+                    LoadAddrOpType::Les => "les",
+                };
+                write!(f, "{mnemonic} {destination}, {source}")
+            }
+            Self::Lahf => write!(f, "lahf"),
+            Self::Sahf => write!(f, "sahf"),
+            Self::Pushf => write!(f, "pushf"),
+            Self::Popf => write!(f, "popf"),
+        }
+    }
+}
@@ -1,8 +1,8 @@
 use std::fmt;
 
-use super::DecodeError;
+use super::{DecodeError, Field, Width};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Register {
     AL, // Low byte of register A.
     BL, // Low byte of register B.
@@ -51,6 +51,15 @@ impl Register {
             }
         }
     }
+
+    /// Returns the width of the register.
+    pub fn width(&self) -> Width {
+        match self {
+            Self::AL | Self::BL | Self::CL | Self::DL => Width::Byte,
+            Self::AH | Self::BH | Self::CH | Self::DH => Width::Byte,
+            _ => Width::Word,
+        }
+    }
 }
 
 impl fmt::Display for Register {
@@ -76,7 +85,7 @@ impl fmt::Display for Register {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SegmentRegister {
     ES, // Extra Segment
     CS, // Code Segment
@@ -93,7 +102,7 @@ impl TryFrom<u8> for SegmentRegister {
             0b01 => Ok(Self::CS),
             0b10 => Ok(Self::SS),
             0b11 => Ok(Self::DS),
-            _ => Err(DecodeError::SegmentRegister),
+            _ => Err(DecodeError::invalid_field(Field::Reg, byte)),
         }
     }
 }
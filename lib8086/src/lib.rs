@@ -4,4 +4,7 @@ pub mod code;
 pub use code::{DecodeError, Decoder};
 
 pub mod sim;
-pub use sim::{Cpu, ExecutionError};
+pub use sim::{
+    Cpu, ExecutionError, ExecutionErrorKind, IoBus, LoggingIoBus, ProgramSizeError, Registers,
+    SnapshotError, StepOutcome, StopReason, Trace, Watchpoint, WatchpointHit,
+};
@@ -1,5 +1,8 @@
 use std::fmt;
 
+/// Bits of the FLAGS word that are not used by any flag and always read as set.
+const RESERVED: u16 = 0xF002;
+
 #[derive(Debug, Default)]
 pub struct Flags {
     pub zero: bool,
@@ -32,6 +35,45 @@ impl Flags {
         self.carry = carry;
         self.aux_carry = aux_carry;
     }
+
+    /// Returns the flags packed in a 16-bit FLAGS word.
+    ///
+    /// The flags occupy the bit positions defined by the 8086: carry (0), parity (2),
+    /// auxiliary carry (4), zero (6), sign (7), trap (8), interrupt-enable (9),
+    /// direction (10) and overflow (11). As on real hardware, the reserved bit 1
+    /// and bits 12 to 15 always read as set.
+    pub fn as_u16(&self) -> u16 {
+        let mut word = RESERVED;
+        let flags = [
+            (self.carry, 0),
+            (self.parity, 2),
+            (self.aux_carry, 4),
+            (self.zero, 6),
+            (self.sign, 7),
+            (self.trap, 8),
+            (self.interrupt_enable, 9),
+            (self.direction, 10),
+            (self.overflow, 11),
+        ];
+        for (flag, pos) in flags {
+            word |= (flag as u16) << pos;
+        }
+        word
+    }
+
+    /// Sets the flags from a packed 16-bit FLAGS word, ignoring the reserved bits.
+    pub fn set_u16(&mut self, word: u16) {
+        let bit = |pos: u16| word & (1 << pos) != 0;
+        self.carry = bit(0);
+        self.parity = bit(2);
+        self.aux_carry = bit(4);
+        self.zero = bit(6);
+        self.sign = bit(7);
+        self.trap = bit(8);
+        self.interrupt_enable = bit(9);
+        self.direction = bit(10);
+        self.overflow = bit(11);
+    }
 }
 
 impl fmt::Display for Flags {
@@ -47,3 +89,23 @@ impl fmt::Display for Flags {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_flags_word() {
+        let mut flags = Flags::default();
+        assert_eq!(flags.as_u16(), 0xf002);
+
+        flags.set_u16(0x0fd5);
+        assert!(flags.carry && flags.parity && flags.aux_carry && flags.zero && flags.sign);
+        assert!(flags.trap && flags.interrupt_enable && flags.direction && flags.overflow);
+        assert_eq!(flags.as_u16(), 0xffd7);
+
+        flags.set_u16(0x0000);
+        assert_eq!(flags.as_u16(), 0xf002);
+    }
+}
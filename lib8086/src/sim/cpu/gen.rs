@@ -1,4 +1,5 @@
 use crate::code::Register;
+use crate::sim::{EResult, ExecutionErrorKind};
 use crate::value::Value;
 use std::fmt;
 
@@ -38,7 +39,9 @@ impl GeneralRegisters {
     }
 
     /// Sets the value of the specified general register.
-    pub fn set(&mut self, reg: &Register, val: Value) {
+    ///
+    /// The value must have the same width as the register.
+    pub fn set(&mut self, reg: &Register, val: Value) -> EResult<()> {
         match val {
             Value::Byte(v) => match reg {
                 Register::AL => self.ax[0] = v as u8,
@@ -49,7 +52,7 @@ impl GeneralRegisters {
                 Register::CH => self.cx[1] = v as u8,
                 Register::DL => self.dx[0] = v as u8,
                 Register::DH => self.dx[1] = v as u8,
-                _ => panic!("Two bytes value for single byte register."),
+                _ => Err(ExecutionErrorKind::WidthMismatch)?,
             },
             Value::Word(v) => {
                 let bytes = v.to_le_bytes();
@@ -62,10 +65,11 @@ impl GeneralRegisters {
                     Register::BP => self.bp = bytes,
                     Register::SI => self.si = bytes,
                     Register::DI => self.di = bytes,
-                    _ => panic!("Single byte for word register."),
+                    _ => Err(ExecutionErrorKind::WidthMismatch)?,
                 }
             }
         };
+        Ok(())
     }
 }
 
@@ -97,17 +101,20 @@ mod tests {
         let mut gen = GeneralRegisters::default();
         assert_eq!(gen.get(&Register::AX), Value::Word(0));
 
-        gen.set(&Register::AL, Value::Byte(8));
+        gen.set(&Register::AL, Value::Byte(8)).unwrap();
         assert_eq!(gen.get(&Register::AX), Value::Word(8));
 
-        gen.set(&Register::AH, Value::Byte(1));
+        gen.set(&Register::AH, Value::Byte(1)).unwrap();
         assert_eq!(gen.get(&Register::AL), Value::Byte(8));
         assert_eq!(gen.get(&Register::AH), Value::Byte(1));
         assert_eq!(gen.get(&Register::AX), Value::Word(264));
 
-        gen.set(&Register::AX, Value::Word(1025));
+        gen.set(&Register::AX, Value::Word(1025)).unwrap();
         assert_eq!(gen.get(&Register::AL), Value::Byte(1));
         assert_eq!(gen.get(&Register::AH), Value::Byte(4));
         assert_eq!(gen.get(&Register::AX), Value::Word(1025));
+
+        assert!(gen.set(&Register::AX, Value::Byte(1)).is_err());
+        assert!(gen.set(&Register::AL, Value::Word(1)).is_err());
     }
 }
@@ -1,4 +1,3 @@
-use std::cmp::Ordering;
 use std::fmt;
 
 mod seg;
@@ -10,21 +9,56 @@ use gen::GeneralRegisters;
 mod flags;
 use flags::Flags;
 
-use super::{EResult, ExecutionError};
-use crate::code::{ops::*, EffectiveAddr, Instruction, Operand, Decoder};
+mod snapshot;
+pub use snapshot::{Registers, SNAPSHOT_VERSION};
+
+mod step;
+pub use step::{MemoryWrite, StepOutcome, StopReason};
+
+mod watch;
+pub use watch::{Watchpoint, WatchpointHit};
+
+mod undo;
+use undo::UndoEntry;
+pub use undo::UNDO_LEN;
+
+use std::cell::Cell;
+use std::collections::VecDeque;
+
+use super::{EResult, ExecutionError, ExecutionErrorKind, IoBus, LoggingIoBus, ProgramSizeError};
+use crate::code::{
+    ops::*, DResult, Decoder, EffectiveAddr, Instruction, MemoryAddr, Operand, Register,
+    SegmentRegister, Width,
+};
 use crate::value::Value;
 
-const MEM_SIZE: usize = 64 * 1024;
+const MEM_SIZE: usize = 1024 * 1024;
 const HALT: u8 = 0xF4;
 
+/// The number of executed instructions whose address is kept in the history.
+pub const HISTORY_LEN: usize = 16;
+
+/// An arithmetic operation on a destination and a source value, given the carry
+/// flag, that returns the result along with the overflow, carry and auxiliary
+/// carry flags.
+type FlaggedOp = fn(&Value, &Value, bool) -> (Value, bool, bool, bool);
 
 #[derive(Debug)]
 pub struct Cpu {
     pub gen_regs: GeneralRegisters,
     pub seg_regs: SegmentRegisters,
     pub flags: Flags,
-    pub mem: [u8; MEM_SIZE],
+    pub mem: Box<[u8]>,
     pub ip: u16,
+    pub io: Box<dyn IoBus>,
+    history: VecDeque<(u16, u16)>,
+    writes: Vec<MemoryWrite>,
+    breakpoints: Vec<(u16, u16)>,
+    watchpoints: Vec<Watchpoint>,
+    read_hit: Cell<Option<(usize, Width)>>,
+    /// The type of the interrupt delivered by the instruction being executed.
+    interrupt: Option<u8>,
+    undo: VecDeque<UndoEntry>,
 }
 
 impl Default for Cpu {
@@ -33,8 +67,16 @@ impl Default for Cpu {
             gen_regs: GeneralRegisters::default(),
             seg_regs: SegmentRegisters::default(),
             flags: Flags::default(),
-            mem: [0u8; MEM_SIZE],
+            mem: vec![0u8; MEM_SIZE].into_boxed_slice(),
             ip: 0,
+            io: Box::new(LoggingIoBus),
+            history: VecDeque::with_capacity(HISTORY_LEN),
+            writes: Vec::new(),
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            read_hit: Cell::new(None),
+            interrupt: None,
+            undo: VecDeque::new(),
         }
     }
 }
@@ -46,109 +88,458 @@ impl Cpu {
         Self::default()
     }
 
+    /// Creates a new CPU with default initial state, whose I/O ports are
+    /// connected to the specified bus.
+    #[inline]
+    pub fn with_io_bus(io: Box<dyn IoBus>) -> Self {
+        Self {
+            io,
+            ..Self::default()
+        }
+    }
+
     /// Returns the next instruction's index in memory.
     ///
     /// It is calculated by starting at the code segment memory, `CS`,
     /// and offsetting by the instruction pointer, `IP`.
     #[inline]
     pub fn ip_abs(&self) -> usize {
-         self.seg_regs.cs() + (self. ip as usize)
+        self.physical_address(SegmentRegister::CS, self.ip)
+    }
+
+    /// Returns the index in memory of the specified offset within a segment.
+    ///
+    /// The physical address is the value of the segment register multiplied by 16
+    /// plus the offset, wrapping around at the end of the 1 MiB address space.
+    #[inline]
+    pub fn physical_address(&self, segment: SegmentRegister, offset: u16) -> usize {
+        self.physical_address_of(self.seg_regs.get(&segment).as_u16(), offset)
+    }
+
+    /// Returns the index in memory of the specified segment and offset values.
+    #[inline]
+    pub fn physical_address_of(&self, segment: u16, offset: u16) -> usize {
+        (((segment as usize) << 4) + offset as usize) % MEM_SIZE
     }
 
     /// Loads the byte code in memory.
     ///
     /// The loaded bytes are saved in the code segment which begins at the address
-    /// stored in the `CS` segment register. To signal the end of the program we 
+    /// stored in the `CS` segment register. To signal the end of the program we
     /// must either store its offset or add a halt instruction. I chose the latter.
-    #[inline]
-    pub fn load_instructions(&mut self, buffer: &[u8]) {
-        self.mem[self.seg_regs.cs()..self.seg_regs.cs() + buffer.len()]
-                    .copy_from_slice(&buffer);
-        self.mem[self.seg_regs.cs() + buffer.len()] = HALT;
+    ///
+    /// The program and its halt instruction must fit both in the 64 KiB code
+    /// segment and before the end of memory, or nothing is loaded.
+    pub fn load_instructions(&mut self, buffer: &[u8]) -> Result<(), ProgramSizeError> {
+        let cs = self.seg_regs.cs();
+        let capacity = (MEM_SIZE - cs).min(u16::MAX as usize + 1) - 1;
+        if buffer.len() > capacity {
+            return Err(ProgramSizeError {
+                size: buffer.len(),
+                capacity,
+            });
+        }
+        self.mem[cs..cs + buffer.len()].copy_from_slice(buffer);
+        self.mem[cs + buffer.len()] = HALT;
+        Ok(())
     }
 
     /// Executes all the loaded instructions.
     ///
-    /// To signal the end of the program we  must either store its offset or add a halt 
-    /// instruction. I chose the latter. When the halt instruction is met, the execution 
-    /// halts and the istruction pointer is decremented by one, the size of the halt 
-    /// instruction.
+    /// To signal the end of the program we  must either store its offset or add a halt
+    /// instruction. I chose the latter. When the halt instruction is met, the execution
+    /// halts and the istruction pointer is left on it. Interrupts, breakpoints and
+    /// watchpoints do not stop the execution.
+    ///
+    /// If an instruction cannot be decoded or executed, the returned error holds its
+    /// address, the instruction and the registers at the time of the failure.
     pub fn execute(&mut self) -> EResult<()> {
         loop {
-            let rem_bytes = &self.mem[self.ip_abs()..];
-            let (instruction, size) = Decoder::try_decode_next(rem_bytes)
-                .expect("Failed to decode next instruction");
-            self.ip += size as u16;
-
-            match instruction {
-                Instruction::Mov(ref op) => self.exec_mov(op),
-                Instruction::Push(ref op) => self.exec_push(op),
-                Instruction::Pop(ref op) => self.exec_pop(op),
-                Instruction::Num(ref op) => self.exec_numeric(op),
-                Instruction::CondJump(ref op) => self.exec_conditional_jump(op),
-                Instruction::Halt => {
-                    self.ip -= 1;
-                    break
+            match self.step().stop {
+                Some(StopReason::Halt) => return Ok(()),
+                Some(StopReason::Error(err)) => return Err(err),
+                _ => {}
+            }
+        }
+    }
+
+    /// Executes instructions until a halt instruction, a breakpoint, a watchpoint or
+    /// an error stops the execution, and returns the outcome of the last step.
+    pub fn run(&mut self) -> StepOutcome {
+        loop {
+            let step = self.step();
+            match step.stop {
+                None | Some(StopReason::Interrupt(_)) => {}
+                Some(_) => return step,
+            }
+        }
+    }
+
+    /// Executes the instruction at `CS:IP`.
+    ///
+    /// The outcome holds the address, the instruction and its size, the writes it
+    /// made to memory, and whether execution should stop after it. A halt
+    /// instruction is not executed: the instruction pointer is left on it, so
+    /// stepping again halts again. If the instruction fails, the changes it made
+    /// are rolled back, leaving `CS:IP` on it, and it is not recorded in the undo
+    /// log. The error holds the registers as they were when it failed.
+    ///
+    /// Execution stops on a watchpoint triggered by the instruction, or on a
+    /// breakpoint at the address of the next instruction. A breakpoint at the
+    /// address of the first executed instruction is therefore not hit.
+    pub fn step(&mut self) -> StepOutcome {
+        let cs = u16::from_le_bytes(self.seg_regs.cs);
+        let ip = self.ip;
+        self.record_history(cs, ip);
+        self.writes.clear();
+        self.read_hit.set(None);
+        self.interrupt = None;
+
+        let (instruction, size) = match Decoder::try_decode_next(&self.mem[self.ip_abs()..]) {
+            Ok(decoded) => decoded,
+            Err(err) => {
+                let err = ExecutionError::from(ExecutionErrorKind::Decode(err)).at(
+                    cs,
+                    ip,
+                    None,
+                    self.registers(),
+                );
+                return StepOutcome {
+                    cs,
+                    ip,
+                    instruction: None,
+                    size: 0,
+                    writes: Vec::new(),
+                    stop: Some(StopReason::Error(err)),
+                };
+            }
+        };
+
+        let stop = if let Instruction::Halt = instruction {
+            Some(StopReason::Halt)
+        } else {
+            let watched = self.watched_values();
+            let before = self.registers();
+            self.ip = self.ip.wrapping_add(size as u16);
+            let stop = match self.exec_instruction(&instruction) {
+                Ok(()) => match self.watchpoint_hit(&watched) {
+                    Some(hit) => Some(StopReason::Watchpoint(hit)),
+                    None if self.is_breakpoint(self.seg_regs.cs(), self.ip) => {
+                        Some(StopReason::Breakpoint)
+                    }
+                    None => self.interrupt.map(StopReason::Interrupt),
                 },
-            }?
+                Err(err) => Some(StopReason::Error(err.at(
+                    cs,
+                    ip,
+                    Some(&instruction),
+                    self.registers(),
+                ))),
+            };
+            match stop {
+                Some(StopReason::Error(_)) => {
+                    let writes = std::mem::take(&mut self.writes);
+                    self.restore(&before, &writes);
+                }
+                _ => self.record_undo(before, self.writes.clone()),
+            }
+            stop
+        };
+        StepOutcome {
+            cs,
+            ip,
+            instruction: Some(instruction),
+            size,
+            writes: std::mem::take(&mut self.writes),
+            stop,
+        }
+    }
+
+    /// Sets a breakpoint at the specified address.
+    pub fn add_breakpoint(&mut self, cs: u16, ip: u16) {
+        if !self.breakpoints.contains(&(cs, ip)) {
+            self.breakpoints.push((cs, ip));
+        }
+    }
+
+    /// Removes the breakpoint at the specified address, returning whether it was set.
+    pub fn remove_breakpoint(&mut self, cs: u16, ip: u16) -> bool {
+        let len = self.breakpoints.len();
+        self.breakpoints.retain(|&bp| bp != (cs, ip));
+        self.breakpoints.len() != len
+    }
+
+    /// Returns the addresses, as `CS:IP` pairs, of the breakpoints.
+    pub fn breakpoints(&self) -> &[(u16, u16)] {
+        &self.breakpoints
+    }
+
+    /// Returns whether there is a breakpoint at the specified physical address.
+    ///
+    /// Breakpoints are compared by physical address, so a breakpoint is hit whatever
+    /// the `CS:IP` pair used to reach it.
+    fn is_breakpoint(&self, cs_abs: usize, ip: u16) -> bool {
+        let idx = (cs_abs + ip as usize) % MEM_SIZE;
+        self.breakpoints
+            .iter()
+            .any(|&(cs, ip)| self.physical_address_of(cs, ip) == idx)
+    }
+
+    /// Adds a watchpoint.
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        if !self.watchpoints.contains(&watchpoint) {
+            self.watchpoints.push(watchpoint);
+        }
+    }
+
+    /// Removes a watchpoint, returning whether it was set.
+    pub fn remove_watchpoint(&mut self, watchpoint: &Watchpoint) -> bool {
+        let len = self.watchpoints.len();
+        self.watchpoints.retain(|wp| wp != watchpoint);
+        self.watchpoints.len() != len
+    }
+
+    /// Returns the watchpoints.
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    /// Returns the values of the registers watched by each watchpoint, or `None` for
+    /// the memory watchpoints.
+    fn watched_values(&self) -> Vec<Option<u16>> {
+        self.watchpoints
+            .iter()
+            .map(|wp| match wp {
+                Watchpoint::Register(reg) => {
+                    let val = self.gen_regs.get(reg).as_u16();
+                    Some(match reg.width() {
+                        Width::Byte => val & 0xFF,
+                        Width::Word => val,
+                    })
+                }
+                Watchpoint::SegmentRegister(reg) => Some(self.seg_regs.get(reg).as_u16()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the first watchpoint access made by the last executed instruction,
+    /// given the values of the watched registers before it.
+    ///
+    /// Memory reads are reported first, then memory writes, then register changes.
+    fn watchpoint_hit(&self, watched: &[Option<u16>]) -> Option<WatchpointHit> {
+        if let Some((address, width)) = self.read_hit.get() {
+            return Some(WatchpointHit::Read { address, width });
+        }
+        self.write_hit(&self.writes)
+            .or_else(|| self.register_hit(watched, &self.watched_values()))
+    }
+
+    /// Returns the first of the writes that triggers a watchpoint.
+    fn write_hit(&self, writes: &[MemoryWrite]) -> Option<WatchpointHit> {
+        writes
+            .iter()
+            .find(|write| self.watchpoints.iter().any(|wp| wp.watches_write(write)))
+            .map(|write| WatchpointHit::Write(*write))
+    }
+
+    /// Returns the first watched register whose value changed, given the values
+    /// returned by `watched_values` before and after an instruction.
+    fn register_hit(&self, before: &[Option<u16>], after: &[Option<u16>]) -> Option<WatchpointHit> {
+        for ((wp, old), new) in self.watchpoints.iter().zip(before).zip(after) {
+            match (wp, old, new) {
+                (Watchpoint::Register(register), Some(old), Some(new)) if old != new => {
+                    return Some(WatchpointHit::Register {
+                        register: *register,
+                        old: *old,
+                        new: *new,
+                    });
+                }
+                (Watchpoint::SegmentRegister(register), Some(old), Some(new)) if old != new => {
+                    return Some(WatchpointHit::SegmentRegister {
+                        register: *register,
+                        old: *old,
+                        new: *new,
+                    });
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Records a read of memory made by the instruction being executed, if it
+    /// triggers a watchpoint and none was triggered before.
+    fn watch_read(&self, idx: usize, width: Width) {
+        if self.read_hit.get().is_none()
+            && self
+                .watchpoints
+                .iter()
+                .any(|wp| wp.watches_read(idx, width))
+        {
+            self.read_hit.set(Some((idx, width)));
+        }
+    }
+
+    /// Decodes the instruction at the specified address.
+    pub fn instruction_at(&self, cs: u16, ip: u16) -> DResult<Instruction> {
+        let idx = self.physical_address_of(cs, ip);
+        Decoder::try_decode_next(&self.mem[idx..])
+    }
+
+    /// Returns the addresses, as `CS:IP` pairs, of the most recently executed
+    /// instructions, oldest first.
+    ///
+    /// At most `HISTORY_LEN` addresses are kept. If execution failed, the last
+    /// one is the address of the faulting instruction.
+    pub fn history(&self) -> impl Iterator<Item = (u16, u16)> + '_ {
+        self.history.iter().copied()
+    }
+
+    /// Records the address of an instruction that is about to be executed.
+    fn record_history(&mut self, cs: u16, ip: u16) {
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_front();
+        }
+        self.history.push_back((cs, ip));
+    }
+
+    /// Returns a copy of the values of all the registers.
+    pub fn registers(&self) -> Registers {
+        Registers {
+            ax: u16::from_le_bytes(self.gen_regs.ax),
+            bx: u16::from_le_bytes(self.gen_regs.bx),
+            cx: u16::from_le_bytes(self.gen_regs.cx),
+            dx: u16::from_le_bytes(self.gen_regs.dx),
+            sp: u16::from_le_bytes(self.gen_regs.sp),
+            bp: u16::from_le_bytes(self.gen_regs.bp),
+            si: u16::from_le_bytes(self.gen_regs.si),
+            di: u16::from_le_bytes(self.gen_regs.di),
+            es: u16::from_le_bytes(self.seg_regs.es),
+            cs: u16::from_le_bytes(self.seg_regs.cs),
+            ss: u16::from_le_bytes(self.seg_regs.ss),
+            ds: u16::from_le_bytes(self.seg_regs.ds),
+            ip: self.ip,
+            flags: self.flags.as_u16(),
+        }
+    }
+
+    /// Sets the values of all the registers.
+    pub fn set_registers(&mut self, registers: &Registers) {
+        self.gen_regs.ax = registers.ax.to_le_bytes();
+        self.gen_regs.bx = registers.bx.to_le_bytes();
+        self.gen_regs.cx = registers.cx.to_le_bytes();
+        self.gen_regs.dx = registers.dx.to_le_bytes();
+        self.gen_regs.sp = registers.sp.to_le_bytes();
+        self.gen_regs.bp = registers.bp.to_le_bytes();
+        self.gen_regs.si = registers.si.to_le_bytes();
+        self.gen_regs.di = registers.di.to_le_bytes();
+        self.seg_regs.es = registers.es.to_le_bytes();
+        self.seg_regs.cs = registers.cs.to_le_bytes();
+        self.seg_regs.ss = registers.ss.to_le_bytes();
+        self.seg_regs.ds = registers.ds.to_le_bytes();
+        self.ip = registers.ip;
+        self.flags.set_u16(registers.flags);
+    }
+
+    /// Executes a single decoded instruction.
+    fn exec_instruction(&mut self, instruction: &Instruction) -> EResult<()> {
+        match instruction {
+            Instruction::Mov(op) => self.exec_mov(op),
+            Instruction::Transfer(op) => self.exec_transfer(op),
+            Instruction::Push(op) => self.exec_push(op),
+            Instruction::Pop(op) => self.exec_pop(op),
+            Instruction::Num(op) => self.exec_numeric(op),
+            Instruction::Adjust(op) => self.exec_adjust(op),
+            Instruction::Logic(op) => self.exec_logic(op),
+            Instruction::Shift(op) => self.exec_shift(op),
+            Instruction::MulDiv(op) => self.exec_muldiv(op),
+            Instruction::String(op) => self.exec_string(op),
+            Instruction::CondJump(op) => self.exec_conditional_jump(op),
+            Instruction::Jump(op) => self.exec_jump(op),
+            Instruction::Call(op) => self.exec_call(op),
+            Instruction::Ret(op) => self.exec_ret(op),
+            Instruction::Interrupt(op) => self.exec_interrupt(op),
+            Instruction::Io(op) => self.exec_io(op),
+            Instruction::Control(op) => self.exec_control(op),
+            Instruction::Convert(op) => self.exec_convert(op),
+            // There is no other bus master, so the lock has no effect.
+            Instruction::Lock(instruction) => self.exec_instruction(instruction),
+            Instruction::Data(_) => Err(ExecutionErrorKind::UndecodedData.into()),
+            Instruction::Halt => Ok(()),
         }
-        Ok(())
     }
 
     /// Returns the value that the operand holds.
     ///
     /// If the operand is a general register, the value is fetched from the Execution Unit (EU);
-    /// otherwise, from the Bus Interface Unit (BIU).
+    /// otherwise, from the Bus Interface Unit (BIU). A memory operand is read as a word.
     fn get_operand_value(&self, operand: &Operand) -> Value {
         match operand {
             Operand::Register(reg) => self.gen_regs.get(reg),
             Operand::SegmentRegister(reg) => self.seg_regs.get(reg),
             Operand::Immediate(val) => val.clone(),
-            Operand::Memory(addr) => {
-                let idx = self
-                    .get_memory_index(addr)
-                    .expect("Failed to get memory value") as usize;
-                Value::word([self.mem[idx], self.mem[idx + 1]])
-            }
+            Operand::Memory(mem) => self.read_memory(self.get_memory_index(mem), Width::Word),
         }
     }
 
-    /// Returns the byte offset equivalend of the effective address.
-    fn get_memory_index(&self, addr: &EffectiveAddr) -> EResult<u16> {
-        let idx = match addr {
+    /// Returns the value that the operand holds, reading `width` bytes if the
+    /// operand is in memory.
+    fn get_sized_operand_value(&self, operand: &Operand, width: Width) -> Value {
+        match operand {
+            Operand::Memory(mem) => self.read_memory(self.get_memory_index(mem), width),
+            _ => self.get_operand_value(operand),
+        }
+    }
+
+    /// Returns the values of the source and destination operands of a two-operand
+    /// instruction.
+    ///
+    /// Memory operands do not carry a width, so it is inferred from the other operand.
+    /// The destination operand cannot be an immediate value.
+    fn get_operand_values(&self, source: &Operand, dest: &Operand) -> EResult<(Value, Value)> {
+        let width = dest.width().or(source.width()).unwrap_or(Width::Word);
+        let sval = self.get_sized_operand_value(source, width);
+        let dval = match dest {
+            Operand::Immediate(_) => Err(ExecutionErrorKind::ImmediateDestination)?,
+            _ => self.get_sized_operand_value(dest, width),
+        };
+        Ok((sval, dval))
+    }
+
+    /// Returns the offset within its segment that the effective address refers to.
+    ///
+    /// The offset is computed modulo 64 KiB, so it wraps around within the segment.
+    fn get_effective_offset(&self, addr: &EffectiveAddr) -> u16 {
+        match addr {
             EffectiveAddr::Direct(idx) => *idx,
             EffectiveAddr::Reg(reg) => self.gen_regs.get(reg).as_u16(),
             EffectiveAddr::RegDisp { base, disp } => {
                 let base = self.gen_regs.get(base).as_u16();
-                let disp = disp.value().expect("");
-                base.checked_add_signed(disp)
-                    .ok_or(ExecutionError::MemoryOffset)?
+                let disp = disp.value().unwrap_or(0);
+                base.wrapping_add_signed(disp)
             }
             EffectiveAddr::RegPair { base, index } => {
                 let base = self.gen_regs.get(base).as_u16();
                 let index = self.gen_regs.get(index).as_u16();
-                base + index
+                base.wrapping_add(index)
             }
             EffectiveAddr::RegPairDisp { base, index, disp } => {
                 let base = self.gen_regs.get(base).as_u16();
                 let index = self.gen_regs.get(index).as_u16();
-                let disp = disp.value().expect("");
-                let sum = base + index;
-                sum.checked_add_signed(disp)
-                    .ok_or(ExecutionError::MemoryOffset)?
+                let disp = disp.value().unwrap_or(0);
+                base.wrapping_add(index).wrapping_add_signed(disp)
             }
-        };
-        Ok(idx)
+        }
     }
 
-    /// Returns the value the destination operand holds.
+    /// Returns the index in memory of a memory operand.
     ///
-    /// The destination operand cannot be an immediate value.
-    fn get_destination_value(&self, operand: &Operand) -> EResult<Value> {
-        match operand {
-            Operand::Immediate(_) => Err(ExecutionError::ImmediateDestination),
-            _ => Ok(self.get_operand_value(operand)),
-        }
+    /// The effective address is offset from its default segment, `SS` for addresses
+    /// based on `BP` and `DS` otherwise, unless the operand has a segment override.
+    fn get_memory_index(&self, mem: &MemoryAddr) -> usize {
+        let offset = self.get_effective_offset(&mem.addr);
+        self.physical_address(mem.segment(), offset)
     }
 
     /// Sets the value of the destination operand, and updates the zero, sign,
@@ -156,80 +547,356 @@ impl Cpu {
     fn set_operand_value(&mut self, dest: &Operand, val: Value) -> EResult<()> {
         self.flags
             .set_zero_sign_parity(val.is_zero(), val.is_negative(), val.is_even());
+        self.write_operand_value(dest, val)
+    }
+
+    /// Sets the value of the destination operand to the result of an arithmetic or
+    /// logical operation, and updates the zero, sign and parity status flags.
+    fn set_result_value(&mut self, dest: &Operand, val: Value) -> EResult<()> {
+        self.flags
+            .set_zero_sign_parity(val.is_zero(), val.is_negative(), val.parity());
+        self.write_operand_value(dest, val)
+    }
+
+    /// Sets the value of the destination operand without affecting the flags.
+    fn write_operand_value(&mut self, dest: &Operand, val: Value) -> EResult<()> {
         match dest {
-            Operand::Register(reg) => self.gen_regs.set(reg, val),
-            Operand::SegmentRegister(reg) => self.seg_regs.set(reg, val),
-            Operand::Memory(addr) => {
-                let idx = self.get_memory_index(addr)? as usize;
-                match val {
-                    Value::Byte(v) => self.mem[idx] = v as u8,
-                    Value::Word(v) => self.mem[idx..idx + 2].copy_from_slice(&v.to_le_bytes()),
-                }
-            }
-            Operand::Immediate(_) => Err(ExecutionError::ImmediateDestination)?,
+            Operand::Register(reg) => self.gen_regs.set(reg, val)?,
+            Operand::SegmentRegister(reg) => self.seg_regs.set(reg, val)?,
+            Operand::Memory(mem) => self.write_memory(self.get_memory_index(mem), val),
+            Operand::Immediate(_) => Err(ExecutionErrorKind::ImmediateDestination)?,
         };
         Ok(())
     }
 
     /// Executes a MOV instruction.
+    ///
+    /// A memory source operand is read with the width of the destination operand.
     fn exec_mov(&mut self, op: &MovOp) -> EResult<()> {
-        let val = self.get_operand_value(&op.source);
+        let width = op.destination.width().unwrap_or(Width::Word);
+        let val = self.get_sized_operand_value(&op.source, width);
         self.set_operand_value(&op.destination, val)
     }
 
-    /// Executes an arithmetic instruction: ADD, ADC, SUB, SBB or CMP.
+    /// Executes a data transfer instruction: XCHG, NOP, XLAT, LEA, LDS, LES, LAHF,
+    /// SAHF, PUSHF or POPF.
+    ///
+    /// None of them affect the flags, apart from SAHF and POPF which overwrite them.
+    fn exec_transfer(&mut self, op: &TransferOp) -> EResult<()> {
+        match op {
+            TransferOp::Xchg {
+                source,
+                destination,
+            } => {
+                let (sval, dval) = self.get_operand_values(source, destination)?;
+                self.write_operand_value(source, dval)?;
+                self.write_operand_value(destination, sval)
+            }
+            TransferOp::Nop => Ok(()),
+            TransferOp::Xlat { segment } => {
+                let bx = u16::from_le_bytes(self.gen_regs.bx);
+                let al = self.gen_regs.get(&Register::AL).as_u16() & 0xff;
+                let segment = segment.unwrap_or(SegmentRegister::DS);
+                let idx = self.physical_address(segment, bx.wrapping_add(al));
+                let val = self.read_memory(idx, Width::Byte);
+                self.gen_regs.set(&Register::AL, val)?;
+                Ok(())
+            }
+            TransferOp::LoadAddr {
+                optype,
+                source: Operand::Memory(mem),
+                destination,
+            } => {
+                let val = match optype {
+                    LoadAddrOpType::Lea => self.get_effective_offset(&mem.addr),
+                    LoadAddrOpType::Lds | LoadAddrOpType::Les => {
+                        let segment = match optype {
+                            LoadAddrOpType::Lds => SegmentRegister::DS,
+                            _ => SegmentRegister::ES,
+                        };
+                        let idx = self.get_memory_index(mem);
+                        let val = self.read_word(idx);
+                        let seg_val = Value::Word(self.read_word(idx + 2) as i16);
+                        self.seg_regs.set(&segment, seg_val)?;
+                        val
+                    }
+                };
+                self.write_operand_value(destination, Value::Word(val as i16))
+            }
+            TransferOp::LoadAddr { .. } => Err(ExecutionErrorKind::AddressOperand.into()),
+            TransferOp::Lahf => {
+                let [low, _] = self.flags.as_u16().to_le_bytes();
+                self.gen_regs.set(&Register::AH, Value::byte(low))?;
+                Ok(())
+            }
+            TransferOp::Sahf => {
+                let ah = self.gen_regs.get(&Register::AH).as_u16() & 0xff;
+                self.flags.set_u16((self.flags.as_u16() & 0xff00) | ah);
+                Ok(())
+            }
+            TransferOp::Pushf => {
+                self.push_word(self.flags.as_u16());
+                Ok(())
+            }
+            TransferOp::Popf => {
+                let flags = self.pop_word();
+                self.flags.set_u16(flags);
+                Ok(())
+            }
+        }
+    }
+
+    /// Executes an arithmetic instruction: ADD, ADC, SUB, SBB, CMP, INC, DEC or NEG.
     fn exec_numeric(&mut self, op: &NumOp) -> EResult<()> {
         use NumOp::*;
 
         // Closure that extracts values from operands, executes the specified operation
-        // and sets the flags and destination operand.
-        let mut exop = |source: &Operand,
-                        destination: &Operand,
-                        f: fn(&Value, &Value) -> (Value, bool, bool, bool)|
-         -> EResult<()> {
-            let sval = self.get_operand_value(source);
-            let dval = self.get_destination_value(destination)?;
+        // given the carry flag, and sets the flags and destination operand.
+        let mut exop = |source: &Operand, destination: &Operand, f: FlaggedOp| -> EResult<()> {
+            let (sval, dval) = self.get_operand_values(source, destination)?;
 
-            let (val, overflow, carry, aux_carry) = f(&dval, &sval);
+            let (val, overflow, carry, aux_carry) = f(&dval, &sval, self.flags.carry);
             self.flags
                 .set_overflow_aux_carry(overflow, carry, aux_carry);
-            self.set_operand_value(destination, val)
+            self.set_result_value(destination, val)
         };
 
         match op {
             Add {
                 source,
                 destination,
-            } => exop(source, destination, |dval, sval| dval.flagged_add(sval)),
+            } => exop(source, destination, |dval, sval, _| dval.flagged_add(sval)),
             Adc {
-                source: _,
-                destination: _,
-            } => {
-                todo!()
-            }
+                source,
+                destination,
+            } => exop(source, destination, |dval, sval, carry| {
+                dval.flagged_carrying_add(sval, carry)
+            }),
             Sub {
                 source,
                 destination,
-            } => exop(source, destination, |dval, sval| dval.flagged_sub(sval)),
+            } => exop(source, destination, |dval, sval, _| dval.flagged_sub(sval)),
             Sbb {
-                source: _,
-                destination: _,
-            } => {
-                todo!()
-            }
+                source,
+                destination,
+            } => exop(source, destination, |dval, sval, carry| {
+                dval.flagged_carrying_sub(sval, carry)
+            }),
             Cmp {
                 source,
                 destination,
             } => {
-                let sval = self.get_operand_value(source);
-                let dval = self.get_destination_value(destination)?;
-                match dval.cmp(&sval) {
-                    Ordering::Equal => self.flags.set_zero_sign_parity(true, false, true),
-                    Ordering::Greater => self.flags.set_zero_sign(false, false),
-                    Ordering::Less => self.flags.set_zero_sign(false, true),
-                }
+                let (sval, dval) = self.get_operand_values(source, destination)?;
+                self.compare(&dval, &sval);
                 Ok(())
             }
+            Inc { destination, width } => {
+                let one = Value::from_u16(1, *width);
+                self.exec_unary(destination, *width, |dval| dval.flagged_add(&one), false)
+            }
+            Dec { destination, width } => {
+                let one = Value::from_u16(1, *width);
+                self.exec_unary(destination, *width, |dval| dval.flagged_sub(&one), false)
+            }
+            Neg { destination, width } => {
+                let zero = Value::from_u16(0, *width);
+                self.exec_unary(destination, *width, |dval| zero.flagged_sub(dval), true)
+            }
+        }
+    }
+
+    /// Executes a decimal adjust instruction: DAA, DAS, AAA, AAS, AAM or AAD.
+    ///
+    /// DAA and DAS adjust `AL` to a packed BCD value, and AAA and AAS adjust it to an
+    /// unpacked one, propagating the decimal carry or borrow to `AH`. These set the
+    /// auxiliary carry and carry flags if a decimal carry or borrow occurred.
+    ///
+    /// AAM splits `AL` into a quotient in `AH` and a remainder in `AL`, and raises a
+    /// type 0 interrupt if the base is zero. AAD combines `AH` and `AL` into `AL` and
+    /// clears `AH`.
+    fn exec_adjust(&mut self, op: &AdjustOp) -> EResult<()> {
+        let al = self.gen_regs.get(&Register::AL).as_u16() as u8;
+        let ah = self.gen_regs.get(&Register::AH).as_u16() as u8;
+        let adjust_low = (al & 0x0F) > 9 || self.flags.aux_carry;
+
+        let al = match op {
+            AdjustOp::Daa | AdjustOp::Das => {
+                let adjust_high = al > 0x99 || self.flags.carry;
+                let adjust = |val: u8, inc: u8| match op {
+                    AdjustOp::Daa => val.overflowing_add(inc),
+                    _ => val.overflowing_sub(inc),
+                };
+                let (val, carry) = if adjust_low {
+                    adjust(al, 0x06)
+                } else {
+                    (al, false)
+                };
+                let (val, _) = if adjust_high {
+                    adjust(val, 0x60)
+                } else {
+                    (val, false)
+                };
+                self.flags.aux_carry = adjust_low;
+                self.flags.carry = adjust_high || carry;
+                val
+            }
+            AdjustOp::Aaa | AdjustOp::Aas => {
+                let (al, ah) = match (op, adjust_low) {
+                    (_, false) => (al, ah),
+                    (AdjustOp::Aaa, true) => (al.wrapping_add(6), ah.wrapping_add(1)),
+                    (_, true) => (al.wrapping_sub(6), ah.wrapping_sub(1)),
+                };
+                self.flags.aux_carry = adjust_low;
+                self.flags.carry = adjust_low;
+                self.gen_regs.set(&Register::AH, Value::byte(ah))?;
+                self.gen_regs.set(&Register::AL, Value::byte(al & 0x0F))?;
+                return Ok(());
+            }
+            AdjustOp::Aam(0) => return self.interrupt(0),
+            AdjustOp::Aam(base) => {
+                self.gen_regs.set(&Register::AH, Value::byte(al / base))?;
+                al % base
+            }
+            AdjustOp::Aad(base) => {
+                self.gen_regs.set(&Register::AH, Value::byte(0))?;
+                al.wrapping_add(ah.wrapping_mul(*base))
+            }
+        };
+        self.set_result_value(&Operand::Register(Register::AL), Value::byte(al))
+    }
+
+    /// Executes a single-operand arithmetic operation and sets the flags and the
+    /// destination operand.
+    ///
+    /// The carry flag is only updated if `sets_carry` is true; INC and DEC preserve it,
+    /// while NEG sets it unless the operand is zero.
+    fn exec_unary(
+        &mut self,
+        destination: &Operand,
+        width: Width,
+        f: impl Fn(&Value) -> (Value, bool, bool, bool),
+        sets_carry: bool,
+    ) -> EResult<()> {
+        let dval = self.get_sized_operand_value(destination, width);
+        let (val, overflow, carry, aux_carry) = f(&dval);
+        let carry = if sets_carry { carry } else { self.flags.carry };
+        self.flags
+            .set_overflow_aux_carry(overflow, carry, aux_carry);
+        self.set_result_value(destination, val)
+    }
+
+    /// Executes a logical instruction: AND, OR, XOR, TEST or NOT.
+    ///
+    /// AND, OR, XOR and TEST clear the carry and overflow flags, and update the
+    /// zero, sign and parity flags according to the result. TEST does not write
+    /// its result to the destination. NOT affects no flags.
+    fn exec_logic(&mut self, op: &LogicOp) -> EResult<()> {
+        use LogicOp::*;
+
+        // Closure that extracts values from operands, executes the specified operation
+        // and sets the flags and, optionally, the destination operand.
+        let mut exop = |source: &Operand,
+                        destination: &Operand,
+                        f: fn(&Value, &Value) -> Value,
+                        write: bool|
+         -> EResult<()> {
+            let (sval, dval) = self.get_operand_values(source, destination)?;
+            let val = f(&dval, &sval);
+            self.flags.overflow = false;
+            self.flags.carry = false;
+            match write {
+                true => self.set_result_value(destination, val),
+                false => {
+                    self.flags
+                        .set_zero_sign_parity(val.is_zero(), val.is_negative(), val.parity());
+                    Ok(())
+                }
+            }
+        };
+
+        match op {
+            And {
+                source,
+                destination,
+            } => exop(source, destination, |dval, sval| dval.and(sval), true),
+            Or {
+                source,
+                destination,
+            } => exop(source, destination, |dval, sval| dval.or(sval), true),
+            Xor {
+                source,
+                destination,
+            } => exop(source, destination, |dval, sval| dval.xor(sval), true),
+            Test {
+                source,
+                destination,
+            } => exop(source, destination, |dval, sval| dval.and(sval), false),
+            Not { destination, width } => {
+                let val = self.get_sized_operand_value(destination, *width).not();
+                self.write_operand_value(destination, val)
+            }
+        }
+    }
+
+    /// Executes a shift or rotate instruction: SHL/SAL, SHR, SAR, ROL, ROR, RCL or RCR.
+    ///
+    /// The operand is shifted one bit at a time, `count` times, with the carry flag
+    /// holding the last bit shifted out. The overflow flag is defined only for
+    /// single-bit shifts, and is left untouched otherwise. Shifts update the zero,
+    /// sign and parity flags; rotates do not. A zero count affects no flags.
+    fn exec_shift(&mut self, op: &ShiftOp) -> EResult<()> {
+        let count = match op.count {
+            ShiftCount::One => 1,
+            ShiftCount::CL => self.gen_regs.cx[0],
+        };
+        if count == 0 {
+            return Ok(());
+        }
+
+        let (msb, mask) = match op.width {
+            Width::Byte => (0x80u16, 0xFFu16),
+            Width::Word => (0x8000u16, 0xFFFFu16),
+        };
+        let original = self
+            .get_sized_operand_value(&op.destination, op.width)
+            .as_u16()
+            & mask;
+
+        let mut val = original;
+        let mut carry = self.flags.carry;
+        for _ in 0..count {
+            let (shifted, carry_out) = match op.optype {
+                ShiftOpType::Shl => (val << 1, val & msb != 0),
+                ShiftOpType::Shr => (val >> 1, val & 1 != 0),
+                ShiftOpType::Sar => ((val >> 1) | (val & msb), val & 1 != 0),
+                ShiftOpType::Rol => ((val << 1) | (val & msb != 0) as u16, val & msb != 0),
+                ShiftOpType::Ror => (
+                    (val >> 1) | if val & 1 != 0 { msb } else { 0 },
+                    val & 1 != 0,
+                ),
+                ShiftOpType::Rcl => ((val << 1) | carry as u16, val & msb != 0),
+                ShiftOpType::Rcr => ((val >> 1) | if carry { msb } else { 0 }, val & 1 != 0),
+            };
+            val = shifted & mask;
+            carry = carry_out;
+        }
+
+        let result = Value::from_u16(val, op.width);
+        if count == 1 {
+            let result_msb = val & msb != 0;
+            self.flags.overflow = match op.optype {
+                ShiftOpType::Shl | ShiftOpType::Rol | ShiftOpType::Rcl => result_msb ^ carry,
+                ShiftOpType::Shr => original & msb != 0,
+                ShiftOpType::Sar => false,
+                ShiftOpType::Ror | ShiftOpType::Rcr => result_msb ^ (val & (msb >> 1) != 0),
+            };
+        }
+        self.flags.carry = carry;
+
+        if op.optype.is_rotate() {
+            self.write_operand_value(&op.destination, result)
+        } else {
+            self.set_result_value(&op.destination, result)
         }
     }
 
@@ -238,12 +905,8 @@ impl Cpu {
     /// Decrements the stack-pointer by 2 and then transfers a word from the source
     /// operand to the top of the stack now pointed to by the stack-pointer.
     fn exec_push(&mut self, op: &PushOp) -> EResult<()> {
-        self.gen_regs.sp[1] += 1;
-
         let val = self.get_operand_value(&op.operand).as_u16();
-        let sp = u16::from_le_bytes(self.gen_regs.sp) as usize;
-        self.mem[sp..sp + 2].copy_from_slice(&val.to_le_bytes());
-
+        self.push_word(val);
         Ok(())
     }
 
@@ -252,11 +915,238 @@ impl Cpu {
     /// Copies the word at the top of the stack to the destination operand
     /// and then increments the stack-pointer by 2.
     fn exec_pop(&mut self, op: &PopOp) -> EResult<()> {
-        let sp = u16::from_le_bytes(self.gen_regs.sp) as usize;
-        let popped = Value::word([self.mem[sp], self.mem[sp + 1]]);
-        self.set_operand_value(&op.operand, popped)?;
+        let popped = Value::Word(self.pop_word() as i16);
+        self.write_operand_value(&op.operand, popped)
+    }
+
+    /// Returns the value of the specified width stored in memory at the specified index.
+    fn read_memory(&self, idx: usize, width: Width) -> Value {
+        match width {
+            Width::Byte => {
+                self.watch_read(idx % MEM_SIZE, Width::Byte);
+                Value::byte(self.mem[idx % MEM_SIZE])
+            }
+            Width::Word => Value::Word(self.read_word(idx) as i16),
+        }
+    }
+
+    /// Stores a value in memory at the specified index.
+    fn write_memory(&mut self, idx: usize, val: Value) {
+        match val {
+            Value::Byte(v) => {
+                let idx = idx % MEM_SIZE;
+                self.record_write(idx, Width::Byte, self.mem[idx] as u16, v as u8 as u16);
+                self.mem[idx] = v as u8;
+            }
+            Value::Word(v) => self.write_word(idx, v as u16),
+        }
+    }
+
+    /// Returns the word stored in memory at the specified index.
+    ///
+    /// The high byte wraps around to the beginning of memory.
+    fn read_word(&self, idx: usize) -> u16 {
+        self.watch_read(idx % MEM_SIZE, Width::Word);
+        self.word_at(idx)
+    }
+
+    /// Returns the word stored in memory at the specified index, without triggering
+    /// watchpoints.
+    fn word_at(&self, idx: usize) -> u16 {
+        u16::from_le_bytes([self.mem[idx % MEM_SIZE], self.mem[(idx + 1) % MEM_SIZE]])
+    }
+
+    /// Stores a word in memory at the specified index.
+    ///
+    /// The high byte wraps around to the beginning of memory.
+    fn write_word(&mut self, idx: usize, val: u16) {
+        self.record_write(idx % MEM_SIZE, Width::Word, self.word_at(idx), val);
+        let [low, high] = val.to_le_bytes();
+        self.mem[idx % MEM_SIZE] = low;
+        self.mem[(idx + 1) % MEM_SIZE] = high;
+    }
+
+    /// Records a write to memory made by the instruction being executed.
+    fn record_write(&mut self, address: usize, width: Width, old: u16, new: u16) {
+        self.writes.push(MemoryWrite {
+            address,
+            width,
+            old,
+            new,
+        });
+    }
+
+    /// Decrements the stack-pointer by 2 and stores the word at the new top
+    /// of the stack.
+    fn push_word(&mut self, val: u16) {
+        let sp = u16::from_le_bytes(self.gen_regs.sp).wrapping_sub(2);
+        self.gen_regs.sp = sp.to_le_bytes();
+        self.write_word(self.physical_address(SegmentRegister::SS, sp), val);
+    }
+
+    /// Returns the word at the top of the stack and increments the stack-pointer
+    /// by 2.
+    fn pop_word(&mut self) -> u16 {
+        let sp = u16::from_le_bytes(self.gen_regs.sp);
+        let val = self.read_word(self.physical_address(SegmentRegister::SS, sp));
+        self.gen_regs.sp = sp.wrapping_add(2).to_le_bytes();
+        val
+    }
+
+    /// Transfers control to the handler of the specified interrupt type.
+    ///
+    /// The FLAGS word, the `CS` register and the instruction pointer are pushed
+    /// onto the stack, the trap and interrupt-enable flags are cleared, and the
+    /// new `IP` and `CS` are loaded from the interrupt vector table, which holds
+    /// a 4-byte vector for each type starting at address 0.
+    fn interrupt(&mut self, vector: u8) -> EResult<()> {
+        self.push_word(self.flags.as_u16());
+        self.push_word(u16::from_le_bytes(self.seg_regs.cs));
+        self.push_word(self.ip);
+        self.flags.trap = false;
+        self.flags.interrupt_enable = false;
+
+        let entry = vector as usize * 4;
+        self.ip = self.read_word(entry);
+        self.seg_regs.cs = self.read_word(entry + 2).to_le_bytes();
+        self.interrupt = Some(vector);
+        Ok(())
+    }
+
+    /// Executes a string instruction: MOVS, CMPS, SCAS, LODS or STOS.
+    ///
+    /// Without a repeat prefix the operation is executed once. With one, it is
+    /// executed while `CX` is non-zero, decrementing `CX` after each iteration. For
+    /// CMPS and SCAS, the repetition also stops when the zero flag no longer
+    /// matches the prefix.
+    fn exec_string(&mut self, op: &StringOp) -> EResult<()> {
+        let Some(rep) = op.rep else {
+            return self.exec_string_once(op);
+        };
+        while u16::from_le_bytes(self.gen_regs.cx) != 0 {
+            self.exec_string_once(op)?;
+            let cx = u16::from_le_bytes(self.gen_regs.cx) - 1;
+            self.gen_regs.cx = cx.to_le_bytes();
+            if op.optype.is_compare() && self.flags.zero != (rep == RepPrefix::Rep) {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes a single iteration of a string instruction.
+    ///
+    /// The source operand is at `DS:SI`, unless its segment is overridden, and the
+    /// destination operand at `ES:DI`. After the operation, the index registers that
+    /// were used are incremented by the operand size, or decremented if the direction
+    /// flag is set.
+    fn exec_string_once(&mut self, op: &StringOp) -> EResult<()> {
+        let acc = match op.width {
+            Width::Byte => Register::AL,
+            Width::Word => Register::AX,
+        };
+        let segment = op.segment.unwrap_or(SegmentRegister::DS);
+        let si = self.physical_address(segment, u16::from_le_bytes(self.gen_regs.si));
+        let di = self.physical_address(SegmentRegister::ES, u16::from_le_bytes(self.gen_regs.di));
+
+        let (uses_si, uses_di) = match op.optype {
+            StringOpType::Movs => {
+                let val = self.read_memory(si, op.width);
+                self.write_memory(di, val);
+                (true, true)
+            }
+            StringOpType::Cmps => {
+                let sval = self.read_memory(si, op.width);
+                let dval = self.read_memory(di, op.width);
+                self.compare(&sval, &dval);
+                (true, true)
+            }
+            StringOpType::Scas => {
+                let aval = self.gen_regs.get(&acc);
+                let dval = self.read_memory(di, op.width);
+                self.compare(&aval, &dval);
+                (false, true)
+            }
+            StringOpType::Lods => {
+                let val = self.read_memory(si, op.width);
+                self.gen_regs.set(&acc, val)?;
+                (true, false)
+            }
+            StringOpType::Stos => {
+                let val = self.gen_regs.get(&acc);
+                self.write_memory(di, val);
+                (false, true)
+            }
+        };
+
+        let step = op.width.n_bytes() as i16;
+        let step = if self.flags.direction { -step } else { step };
+        if uses_si {
+            let si = u16::from_le_bytes(self.gen_regs.si).wrapping_add_signed(step);
+            self.gen_regs.si = si.to_le_bytes();
+        }
+        if uses_di {
+            let di = u16::from_le_bytes(self.gen_regs.di).wrapping_add_signed(step);
+            self.gen_regs.di = di.to_le_bytes();
+        }
+        Ok(())
+    }
+
+    /// Subtracts the second value from the first and sets all the status flags
+    /// according to the result, which is discarded.
+    fn compare(&mut self, val1: &Value, val2: &Value) {
+        let (val, overflow, carry, aux_carry) = val1.flagged_sub(val2);
+        self.flags
+            .set_overflow_aux_carry(overflow, carry, aux_carry);
+        self.flags
+            .set_zero_sign_parity(val.is_zero(), val.is_negative(), val.parity());
+    }
+
+    /// Executes a multiply or divide instruction: MUL, IMUL, DIV or IDIV.
+    ///
+    /// Byte operations multiply `AL` into `AX`, or divide `AX` into a quotient in `AL`
+    /// and a remainder in `AH`. Word operations multiply `AX` into `DX:AX`, or divide
+    /// `DX:AX` into a quotient in `AX` and a remainder in `DX`.
+    ///
+    /// Multiplications set the carry and overflow flags if the upper half of the
+    /// result is significant. A zero divisor, or a quotient that does not fit in the
+    /// destination, raises a type 0 interrupt.
+    fn exec_muldiv(&mut self, op: &MulDivOp) -> EResult<()> {
+        let (low, high) = match op.width {
+            Width::Byte => (Register::AL, Register::AH),
+            Width::Word => (Register::AX, Register::DX),
+        };
+        let sval = self.get_sized_operand_value(&op.source, op.width);
+        let lval = self.gen_regs.get(&low);
 
-        self.gen_regs.sp[1] += 2;
+        let (lres, hres) = match op.optype {
+            MulDivOpType::Mul | MulDivOpType::Imul => {
+                let (lres, hres) = match op.optype {
+                    MulDivOpType::Mul => lval.widening_mul(&sval),
+                    _ => lval.widening_imul(&sval),
+                };
+                let significant = match op.optype {
+                    MulDivOpType::Mul => !hres.is_zero(),
+                    _ => hres.as_signed() != if lres.is_negative() { -1 } else { 0 },
+                };
+                self.flags.carry = significant;
+                self.flags.overflow = significant;
+                (lres, hres)
+            }
+            MulDivOpType::Div | MulDivOpType::Idiv => {
+                let hval = self.gen_regs.get(&high);
+                let result = match op.optype {
+                    MulDivOpType::Div => Value::checked_div(&hval, &lval, &sval),
+                    _ => Value::checked_idiv(&hval, &lval, &sval),
+                };
+                match result {
+                    Some(result) => result,
+                    None => return self.interrupt(0),
+                }
+            }
+        };
+        self.gen_regs.set(&low, lres)?;
+        self.gen_regs.set(&high, hres)?;
         Ok(())
     }
 
@@ -265,7 +1155,156 @@ impl Cpu {
             self.ip = self
                 .ip
                 .checked_add_signed(offset.into())
-                .ok_or(ExecutionError::InstructionOffset)?;
+                .ok_or(ExecutionError::from(ExecutionErrorKind::InstructionOffset))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the segment and offset of the target of an unconditional jump or call.
+    ///
+    /// Near targets keep the current code segment, and relative offsets wrap around
+    /// within it.
+    fn get_target(&self, target: &Target) -> EResult<(u16, u16)> {
+        let cs = u16::from_le_bytes(self.seg_regs.cs);
+        match target {
+            Target::Short(inc) => Ok((cs, self.ip.wrapping_add_signed(*inc as i16))),
+            Target::Near(inc) => Ok((cs, self.ip.wrapping_add_signed(*inc))),
+            Target::Far { segment, offset } => Ok((*segment, *offset)),
+            Target::Indirect(operand) => Ok((cs, self.get_operand_value(operand).as_u16())),
+            Target::IndirectFar(Operand::Memory(mem)) => {
+                let idx = self.get_memory_index(mem);
+                Ok((self.read_word(idx + 2), self.read_word(idx)))
+            }
+            Target::IndirectFar(_) => Err(ExecutionErrorKind::FarTarget.into()),
+        }
+    }
+
+    /// Executes an unconditional JMP instruction.
+    ///
+    /// Transfers control to the target, loading the code segment as well if the
+    /// jump is far.
+    fn exec_jump(&mut self, op: &JumpOp) -> EResult<()> {
+        let (cs, ip) = self.get_target(&op.target)?;
+        self.seg_regs.cs = cs.to_le_bytes();
+        self.ip = ip;
+        Ok(())
+    }
+
+    /// Executes a CALL instruction.
+    ///
+    /// Pushes the return address onto the stack, which for far calls includes the
+    /// code segment, and transfers control to the target.
+    fn exec_call(&mut self, op: &CallOp) -> EResult<()> {
+        let (cs, ip) = self.get_target(&op.target)?;
+        if matches!(op.target, Target::Far { .. } | Target::IndirectFar(_)) {
+            self.push_word(u16::from_le_bytes(self.seg_regs.cs));
+        }
+        self.push_word(self.ip);
+        self.seg_regs.cs = cs.to_le_bytes();
+        self.ip = ip;
+        Ok(())
+    }
+
+    /// Executes a RET or RETF instruction.
+    ///
+    /// Pops the return address from the stack, which for far returns includes the
+    /// code segment, and then discards the specified number of parameter bytes.
+    fn exec_ret(&mut self, op: &RetOp) -> EResult<()> {
+        self.ip = self.pop_word();
+        if op.far {
+            self.seg_regs.cs = self.pop_word().to_le_bytes();
+        }
+        if let Some(pop) = op.pop {
+            let sp = u16::from_le_bytes(self.gen_regs.sp).wrapping_add(pop);
+            self.gen_regs.sp = sp.to_le_bytes();
+        }
+        Ok(())
+    }
+
+    /// Executes an INT, INT 3, INTO or IRET instruction.
+    ///
+    /// INTO only interrupts if the overflow flag is set. IRET pops the instruction
+    /// pointer, the `CS` register and the FLAGS word pushed when the interrupt
+    /// was raised.
+    fn exec_interrupt(&mut self, op: &InterruptOp) -> EResult<()> {
+        match op {
+            InterruptOp::Int(vector) => self.interrupt(*vector),
+            InterruptOp::Int3 => self.interrupt(3),
+            InterruptOp::Into if self.flags.overflow => self.interrupt(4),
+            InterruptOp::Into => Ok(()),
+            InterruptOp::Iret => {
+                self.ip = self.pop_word();
+                self.seg_regs.cs = self.pop_word().to_le_bytes();
+                let flags = self.pop_word();
+                self.flags.set_u16(flags);
+                Ok(())
+            }
+        }
+    }
+
+    /// Executes an IN or OUT instruction.
+    ///
+    /// The transfer is delegated to the I/O bus. Word transfers access the port
+    /// and the one following it.
+    fn exec_io(&mut self, op: &IoOp) -> EResult<()> {
+        let port = match op.port {
+            Port::Immediate(port) => port as u16,
+            Port::DX => u16::from_le_bytes(self.gen_regs.dx),
+        };
+        let acc = op.accumulator();
+        match (op.optype, op.width) {
+            (IoOpType::In, Width::Byte) => {
+                let val = self.io.read_port(port);
+                self.gen_regs.set(&acc, Value::byte(val))?;
+            }
+            (IoOpType::In, Width::Word) => {
+                let low = self.io.read_port(port);
+                let high = self.io.read_port(port.wrapping_add(1));
+                self.gen_regs.set(&acc, Value::word([low, high]))?;
+            }
+            (IoOpType::Out, width) => {
+                let [low, high] = self.gen_regs.get(&acc).as_u16().to_le_bytes();
+                self.io.write_port(port, low);
+                if width == Width::Word {
+                    self.io.write_port(port.wrapping_add(1), high);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes a flag or processor control instruction.
+    ///
+    /// There is no coprocessor attached, so WAIT returns immediately and ESC does
+    /// nothing.
+    fn exec_control(&mut self, op: &ControlOp) -> EResult<()> {
+        match op {
+            ControlOp::Clc => self.flags.carry = false,
+            ControlOp::Stc => self.flags.carry = true,
+            ControlOp::Cmc => self.flags.carry = !self.flags.carry,
+            ControlOp::Cld => self.flags.direction = false,
+            ControlOp::Std => self.flags.direction = true,
+            ControlOp::Cli => self.flags.interrupt_enable = false,
+            ControlOp::Sti => self.flags.interrupt_enable = true,
+            ControlOp::Wait | ControlOp::Esc { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Executes a CBW or CWD instruction, which extend the sign of the accumulator
+    /// without affecting the flags.
+    fn exec_convert(&mut self, op: &ConvertOp) -> EResult<()> {
+        match op {
+            ConvertOp::Cbw => {
+                let al = self.gen_regs.get(&Register::AL);
+                self.gen_regs
+                    .set(&Register::AX, Value::Word(al.as_signed() as i16))?;
+            }
+            ConvertOp::Cwd => {
+                let ax = self.gen_regs.get(&Register::AX);
+                let dx = if ax.is_negative() { -1 } else { 0 };
+                self.gen_regs.set(&Register::DX, Value::Word(dx))?;
+            }
         }
         Ok(())
     }
@@ -278,7 +1317,7 @@ impl Cpu {
         // Decrements the CX register by 1 and returns if the updated
         // value it is non-zero.
         let decrement_cx = |cx: &mut [u8; 2]| -> bool {
-            let updated = u16::from_le_bytes(*cx) - 1;
+            let updated = u16::from_le_bytes(*cx).wrapping_sub(1);
             *cx = updated.to_le_bytes();
             updated != 0
         };
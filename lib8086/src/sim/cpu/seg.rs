@@ -1,6 +1,7 @@
 use std::fmt;
 
 use crate::code::SegmentRegister;
+use crate::sim::{EResult, ExecutionErrorKind};
 use crate::value::Value;
 
 #[derive(Debug, Default)]
@@ -23,8 +24,8 @@ impl SegmentRegisters {
         Value::word(bytes)
     }
 
-    /// Sets the value of the specified segment register.
-    pub fn set(&mut self, seg_reg: &SegmentRegister, val: Value) {
+    /// Sets the value of the specified segment register, which must be a word.
+    pub fn set(&mut self, seg_reg: &SegmentRegister, val: Value) -> EResult<()> {
         match val {
             Value::Word(v) => {
                 let bytes = v.to_le_bytes();
@@ -35,18 +36,19 @@ impl SegmentRegisters {
                     SegmentRegister::DS => self.ds = bytes,
                 };
             }
-            _ => panic!("The value of a segment register can be set to a word value."),
+            _ => Err(ExecutionErrorKind::WidthMismatch)?,
         }
+        Ok(())
     }
 
-    /// Returns the Code Segment memory stored in the `CS` segment
-    /// register.
+    /// Returns the physical address at which the code segment begins.
     ///
-    /// The value is returned as usize because it's used in memory
-    /// indexing.
+    /// Segments start at 16-byte boundaries, so the value stored in the `CS`
+    /// segment register is multiplied by 16. The value is returned as usize
+    /// because it's used in memory indexing.
     #[inline]
     pub fn cs(&self) -> usize {
-        u16::from_le_bytes(self.cs) as usize
+        (u16::from_le_bytes(self.cs) as usize) << 4
     }
 }
 
@@ -74,7 +76,8 @@ mod tests {
         let mut segs = SegmentRegisters::default();
         assert_eq!(segs.get(&SegmentRegister::ES), Value::Word(0));
 
-        segs.set(&SegmentRegister::ES, Value::Word(10));
+        segs.set(&SegmentRegister::ES, Value::Word(10)).unwrap();
         assert_eq!(segs.get(&SegmentRegister::ES), Value::Word(10));
+        assert!(segs.set(&SegmentRegister::ES, Value::Byte(1)).is_err());
     }
 }
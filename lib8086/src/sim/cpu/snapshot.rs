@@ -0,0 +1,194 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use super::Cpu;
+use crate::sim::SnapshotError;
+
+/// The bytes every snapshot starts with.
+const MAGIC: &[u8; 8] = b"SIM8086\0";
+
+/// The version of the snapshot format written by `Cpu::save_snapshot`.
+///
+/// Version 1 is laid out as follows, with all numbers little-endian:
+/// - the magic bytes `SIM8086\0` and the version, as a 16-bit number;
+/// - the registers `AX`, `BX`, `CX`, `DX`, `SP`, `BP`, `SI`, `DI`, `ES`, `CS`, `SS`,
+///   `DS`, `IP` and the FLAGS word, as 16-bit numbers;
+/// - the size of the memory, as a 32-bit number, followed by the memory;
+/// - the size of the device state, as a 32-bit number, followed by the device
+///   state. No device state is saved yet, so the size is zero.
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+/// A copy of the values of all the registers of the CPU.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Registers {
+    pub ax: u16,
+    pub bx: u16,
+    pub cx: u16,
+    pub dx: u16,
+    pub sp: u16,
+    pub bp: u16,
+    pub si: u16,
+    pub di: u16,
+    pub es: u16,
+    pub cs: u16,
+    pub ss: u16,
+    pub ds: u16,
+    pub ip: u16,
+    pub flags: u16,
+}
+
+impl Registers {
+    /// Returns the names and values of all the registers except the flags.
+    pub fn named(&self) -> [(&'static str, u16); 13] {
+        [
+            ("ax", self.ax),
+            ("bx", self.bx),
+            ("cx", self.cx),
+            ("dx", self.dx),
+            ("sp", self.sp),
+            ("bp", self.bp),
+            ("si", self.si),
+            ("di", self.di),
+            ("es", self.es),
+            ("cs", self.cs),
+            ("ss", self.ss),
+            ("ds", self.ds),
+            ("ip", self.ip),
+        ]
+    }
+
+    /// Returns a mutable reference to the register with the specified name, which
+    /// is one of the names returned by `named` or `flags`.
+    pub fn named_mut(&mut self, name: &str) -> Option<&mut u16> {
+        let reg = match name {
+            "ax" => &mut self.ax,
+            "bx" => &mut self.bx,
+            "cx" => &mut self.cx,
+            "dx" => &mut self.dx,
+            "sp" => &mut self.sp,
+            "bp" => &mut self.bp,
+            "si" => &mut self.si,
+            "di" => &mut self.di,
+            "es" => &mut self.es,
+            "cs" => &mut self.cs,
+            "ss" => &mut self.ss,
+            "ds" => &mut self.ds,
+            "ip" => &mut self.ip,
+            "flags" => &mut self.flags,
+            _ => return None,
+        };
+        Some(reg)
+    }
+}
+
+impl Registers {
+    /// Returns the values of all the registers in snapshot order.
+    fn words(&self) -> [u16; 14] {
+        [
+            self.ax, self.bx, self.cx, self.dx, self.sp, self.bp, self.si, self.di, self.es,
+            self.cs, self.ss, self.ds, self.ip, self.flags,
+        ]
+    }
+
+    /// Creates the registers from their values in snapshot order.
+    fn from_words(words: [u16; 14]) -> Self {
+        let [ax, bx, cx, dx, sp, bp, si, di, es, cs, ss, ds, ip, flags] = words;
+        Self {
+            ax,
+            bx,
+            cx,
+            dx,
+            sp,
+            bp,
+            si,
+            di,
+            es,
+            cs,
+            ss,
+            ds,
+            ip,
+            flags,
+        }
+    }
+}
+
+impl Cpu {
+    /// Writes a snapshot of the registers, the flags and the memory.
+    pub fn save_snapshot(&self, mut writer: impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+        for word in self.registers().words() {
+            writer.write_all(&word.to_le_bytes())?;
+        }
+        writer.write_all(&(self.mem.len() as u32).to_le_bytes())?;
+        writer.write_all(&self.mem)?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.flush()
+    }
+
+    /// Restores the registers, the flags and the memory from a snapshot.
+    ///
+    /// The CPU is left unchanged if the snapshot cannot be read. The history of
+    /// executed instructions and the undo log are cleared, while breakpoints and
+    /// watchpoints are kept.
+    pub fn load_snapshot(&mut self, mut reader: impl Read) -> Result<(), SnapshotError> {
+        let mut magic = [0u8; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        let version = read_u16(&mut reader)?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let mut words = [0u16; 14];
+        for word in &mut words {
+            *word = read_u16(&mut reader)?;
+        }
+        let size = read_u32(&mut reader)? as usize;
+        if size != self.mem.len() {
+            return Err(SnapshotError::MemorySize(size));
+        }
+        let mut mem = vec![0u8; size];
+        reader.read_exact(&mut mem)?;
+        // There are no devices to restore yet.
+        let devices = read_u32(&mut reader)? as u64;
+        if io::copy(&mut reader.by_ref().take(devices), &mut io::sink())? != devices {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+
+        self.mem.copy_from_slice(&mem);
+        self.set_registers(&Registers::from_words(words));
+        self.history.clear();
+        self.undo.clear();
+        Ok(())
+    }
+}
+
+fn read_u16(reader: &mut impl Read) -> io::Result<u16> {
+    let mut bytes = [0u8; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "ax={:04x} bx={:04x} cx={:04x} dx={:04x} sp={:04x} bp={:04x} si={:04x} di={:04x}",
+            self.ax, self.bx, self.cx, self.dx, self.sp, self.bp, self.si, self.di
+        )?;
+        write!(
+            f,
+            "es={:04x} cs={:04x} ss={:04x} ds={:04x} ip={:04x} flags={:04x}",
+            self.es, self.cs, self.ss, self.ds, self.ip, self.flags
+        )
+    }
+}
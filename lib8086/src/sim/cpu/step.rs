@@ -0,0 +1,52 @@
+use crate::code::{Instruction, Width};
+use crate::sim::ExecutionError;
+
+use super::WatchpointHit;
+
+/// The result of executing a single instruction with `Cpu::step`.
+#[derive(Debug)]
+pub struct StepOutcome {
+    /// The code segment of the instruction.
+    pub cs: u16,
+    /// The offset of the instruction within the code segment.
+    pub ip: u16,
+    /// The decoded instruction, or `None` if it could not be decoded.
+    pub instruction: Option<Instruction>,
+    /// The size of the instruction in bytes.
+    pub size: usize,
+    /// The writes to memory made by the instruction, in order.
+    pub writes: Vec<MemoryWrite>,
+    /// The reason to stop execution after this instruction, if any.
+    pub stop: Option<StopReason>,
+}
+
+/// The reason execution stopped after a step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StopReason {
+    /// A halt instruction was met. The instruction pointer is left on it.
+    Halt,
+    /// The next instruction is at a breakpoint.
+    Breakpoint,
+    /// The instruction triggered a watchpoint.
+    Watchpoint(WatchpointHit),
+    /// The instruction raised the interrupt with the specified type, and control
+    /// was transferred to its handler. Besides INT, INT 3 and INTO, divide errors
+    /// raise a type 0 interrupt.
+    Interrupt(u8),
+    /// The instruction could not be decoded or executed.
+    ///
+    /// Whatever the instruction changed before it failed is rolled back.
+    Error(ExecutionError),
+}
+
+/// A write of a byte or a word to memory.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemoryWrite {
+    /// The physical address of the written value.
+    pub address: usize,
+    pub width: Width,
+    /// The value in memory before the write.
+    pub old: u16,
+    /// The written value.
+    pub new: u16,
+}
@@ -0,0 +1,78 @@
+use super::{Cpu, MemoryWrite, Registers, StopReason, MEM_SIZE};
+use crate::code::Width;
+
+/// The number of executed instructions that can be undone.
+pub const UNDO_LEN: usize = 4096;
+
+/// The changes made by an executed instruction, to undo it.
+#[derive(Debug)]
+pub(super) struct UndoEntry {
+    /// The registers before the instruction.
+    registers: Registers,
+    /// The writes to memory made by the instruction, in order.
+    writes: Vec<MemoryWrite>,
+}
+
+impl Cpu {
+    /// Records the changes made by an executed instruction in the undo log.
+    pub(super) fn record_undo(&mut self, registers: Registers, writes: Vec<MemoryWrite>) {
+        if self.undo.len() == UNDO_LEN {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(UndoEntry { registers, writes });
+    }
+
+    /// Undoes the last executed instruction, restoring the registers, the flags and
+    /// the memory it changed, and returns its address, which is the new `CS:IP`.
+    ///
+    /// Returns `None` if there is no instruction to undo: at most `UNDO_LEN`
+    /// instructions are kept. Port I/O is not undone.
+    pub fn step_back(&mut self) -> Option<(u16, u16)> {
+        let entry = self.undo.pop_back()?;
+        self.restore(&entry.registers, &entry.writes);
+
+        // Forget the instruction, and the halts met after it, from the history.
+        let addr = (entry.registers.cs, entry.registers.ip);
+        while let Some(last) = self.history.pop_back() {
+            if last == addr {
+                break;
+            }
+        }
+        Some(addr)
+    }
+
+    /// Restores the registers, and the memory changed by the writes, to their
+    /// values before the writes.
+    pub(super) fn restore(&mut self, registers: &Registers, writes: &[MemoryWrite]) {
+        for write in writes.iter().rev() {
+            let [low, high] = write.old.to_le_bytes();
+            self.mem[write.address] = low;
+            if write.width == Width::Word {
+                self.mem[(write.address + 1) % MEM_SIZE] = high;
+            }
+        }
+        self.set_registers(registers);
+    }
+
+    /// Undoes instructions until one triggers a write or register watchpoint, or
+    /// the next instruction is at a breakpoint, and returns the reason it stopped.
+    ///
+    /// Returns `None` if the undo log was exhausted first. Read watchpoints are not
+    /// checked, since reads are not recorded.
+    pub fn run_back(&mut self) -> Option<StopReason> {
+        loop {
+            let entry = self.undo.back()?;
+            let hit = self.write_hit(&entry.writes);
+            let after = self.watched_values();
+            self.step_back();
+            let hit = hit.or_else(|| self.register_hit(&self.watched_values(), &after));
+
+            if let Some(hit) = hit {
+                return Some(StopReason::Watchpoint(hit));
+            }
+            if self.is_breakpoint(self.seg_regs.cs(), self.ip) {
+                return Some(StopReason::Breakpoint);
+            }
+        }
+    }
+}
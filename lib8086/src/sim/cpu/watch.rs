@@ -0,0 +1,83 @@
+use std::ops::Range;
+
+use crate::code::{Register, SegmentRegister, Width};
+
+use super::MemoryWrite;
+
+/// A condition on the accesses of an instruction that stops execution after it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Watchpoint {
+    /// Triggers when any byte in the range of physical addresses is read.
+    Read(Range<usize>),
+    /// Triggers when any byte in the range of physical addresses is written.
+    Write(Range<usize>),
+    /// Triggers when the value of the general register changes.
+    Register(Register),
+    /// Triggers when the value of the segment register changes.
+    SegmentRegister(SegmentRegister),
+}
+
+impl Watchpoint {
+    /// Returns whether the watchpoint triggers on a read of the value of the
+    /// specified width at the specified address.
+    pub(super) fn watches_read(&self, address: usize, width: Width) -> bool {
+        match self {
+            Self::Read(range) => overlaps(range, address, width),
+            _ => false,
+        }
+    }
+
+    /// Returns whether the watchpoint triggers on the write.
+    pub(super) fn watches_write(&self, write: &MemoryWrite) -> bool {
+        match self {
+            Self::Write(range) => overlaps(range, write.address, write.width),
+            _ => false,
+        }
+    }
+}
+
+/// The access that triggered a watchpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WatchpointHit {
+    /// A value of the specified width was read at the physical address.
+    Read { address: usize, width: Width },
+    /// Memory was written.
+    Write(MemoryWrite),
+    /// The value of the general register changed.
+    Register {
+        register: Register,
+        old: u16,
+        new: u16,
+    },
+    /// The value of the segment register changed.
+    SegmentRegister {
+        register: SegmentRegister,
+        old: u16,
+        new: u16,
+    },
+}
+
+/// Returns whether a value of the specified width at the address overlaps the range.
+fn overlaps(range: &Range<usize>, address: usize, width: Width) -> bool {
+    let len = match width {
+        Width::Byte => 1,
+        Width::Word => 2,
+    };
+    address < range.end && range.start < address + len
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_overlaps() {
+        assert!(overlaps(&(0x10..0x12), 0x10, Width::Byte));
+        assert!(overlaps(&(0x10..0x12), 0x11, Width::Word));
+        assert!(overlaps(&(0x10..0x12), 0x0f, Width::Word));
+        assert!(!overlaps(&(0x10..0x12), 0x0f, Width::Byte));
+        assert!(!overlaps(&(0x10..0x12), 0x12, Width::Word));
+        assert!(!overlaps(&(0x10..0x10), 0x10, Width::Word));
+    }
+}
@@ -1,8 +1,175 @@
-#[derive(Debug)]
-pub enum ExecutionError {
+use std::{error, fmt, io};
+
+use super::Registers;
+use crate::code::{DecodeError, Instruction};
+
+/// The reason the execution of an instruction failed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionErrorKind {
+    /// The instruction at `CS:IP` could not be decoded.
+    Decode(DecodeError),
+    /// The destination operand is an immediate value.
     ImmediateDestination,
+    /// A relative jump leaves the code segment.
     InstructionOffset,
-    MemoryOffset,
+    /// A far indirect jump or call through an operand that is not in memory.
+    FarTarget,
+    /// LEA, LDS or LES with a source operand that is not in memory.
+    AddressOperand,
+    /// The bytes at `CS:IP` were not decoded as an instruction.
+    UndecodedData,
+    /// A byte value was written to a word register, or the other way around.
+    WidthMismatch,
+}
+
+impl fmt::Display for ExecutionErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decode(err) => write!(f, "{}", err.kind),
+            Self::ImmediateDestination => write!(f, "destination operand is an immediate value"),
+            Self::InstructionOffset => write!(f, "jump target is outside the code segment"),
+            Self::FarTarget => write!(f, "far jump or call target is not in memory"),
+            Self::AddressOperand => write!(f, "address operand is not in memory"),
+            Self::UndecodedData => write!(f, "cannot execute undecoded data"),
+            Self::WidthMismatch => write!(f, "value and destination have different widths"),
+        }
+    }
+}
+
+/// An error that occurred while executing a program.
+///
+/// Along with the reason of the failure, the error records the address of the
+/// faulting instruction, the instruction itself if it could be decoded, and the
+/// registers at the time of the failure.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecutionError {
+    pub kind: ExecutionErrorKind,
+    pub cs: u16,
+    pub ip: u16,
+    pub instruction: Option<String>,
+    pub registers: Registers,
+}
+
+impl ExecutionError {
+    /// Records the faulting instruction and the state of the CPU in the error.
+    pub(crate) fn at(
+        self,
+        cs: u16,
+        ip: u16,
+        instruction: Option<&Instruction>,
+        registers: Registers,
+    ) -> Self {
+        Self {
+            cs,
+            ip,
+            instruction: instruction.map(|instruction| instruction.to_string()),
+            registers,
+            ..self
+        }
+    }
+}
+
+impl From<ExecutionErrorKind> for ExecutionError {
+    fn from(kind: ExecutionErrorKind) -> Self {
+        Self {
+            kind,
+            cs: 0,
+            ip: 0,
+            instruction: None,
+            registers: Registers::default(),
+        }
+    }
+}
+
+impl fmt::Display for ExecutionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at {:04x}:{:04x}", self.kind, self.cs, self.ip)?;
+        match (&self.instruction, &self.kind) {
+            (Some(instruction), _) => write!(f, " (`{instruction}`)"),
+            (None, ExecutionErrorKind::Decode(err)) if !err.bytes.is_empty() => {
+                let bytes = err
+                    .bytes
+                    .iter()
+                    .map(|byte| format!("{byte:02x}"))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                write!(f, " (bytes: {bytes})")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl error::Error for ExecutionError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match &self.kind {
+            ExecutionErrorKind::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
 }
 
 pub type EResult<T> = Result<T, ExecutionError>;
+
+/// A program that does not fit in memory, along with the halt instruction that
+/// ends it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgramSizeError {
+    /// The size of the program in bytes.
+    pub size: usize,
+    /// The largest program size that fits in the code segment.
+    pub capacity: usize,
+}
+
+impl fmt::Display for ProgramSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "program is {} bytes, but at most {} bytes fit in the code segment",
+            self.size, self.capacity
+        )
+    }
+}
+
+impl error::Error for ProgramSizeError {}
+
+/// An error that occurred while loading a CPU snapshot.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The snapshot could not be read.
+    Io(io::Error),
+    /// The data does not start with the snapshot magic bytes.
+    NotASnapshot,
+    /// The snapshot was written in a format version that is not supported.
+    UnsupportedVersion(u16),
+    /// The snapshot holds a memory image of the wrong size.
+    MemorySize(usize),
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "cannot read snapshot: {err}"),
+            Self::NotASnapshot => write!(f, "not a sim8086 snapshot"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {version}")
+            }
+            Self::MemorySize(size) => write!(f, "snapshot memory is {size} bytes"),
+        }
+    }
+}
+
+impl error::Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt;
+
+/// `IoBus` is the interface between the CPU and the devices attached to its I/O
+/// ports.
+///
+/// The 8086 addresses up to 64 KiB of 8-bit ports. Word transfers access two
+/// consecutive ports, with the low byte at the lower port.
+pub trait IoBus {
+    /// Returns the byte read from the specified port.
+    fn read_port(&mut self, port: u16) -> u8;
+
+    /// Writes a byte to the specified port.
+    fn write_port(&mut self, port: u16, val: u8);
+}
+
+impl fmt::Debug for dyn IoBus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("IoBus")
+    }
+}
+
+/// The default I/O bus, with no attached devices.
+///
+/// Every access is logged to the standard error. Reads return `0xFF`, which is
+/// what a floating data bus reads as on real hardware.
+#[derive(Debug, Default)]
+pub struct LoggingIoBus;
+
+impl IoBus for LoggingIoBus {
+    fn read_port(&mut self, port: u16) -> u8 {
+        eprintln!("I/O read from port 0x{port:04x}");
+        0xFF
+    }
+
+    fn write_port(&mut self, port: u16, val: u8) {
+        eprintln!("I/O write of 0x{val:02x} to port 0x{port:04x}");
+    }
+}
@@ -1,5 +1,11 @@
 pub mod error;
-pub use error::{EResult, ExecutionError};
+pub use error::{EResult, ExecutionError, ExecutionErrorKind, ProgramSizeError, SnapshotError};
 
 pub mod cpu;
-pub use cpu::Cpu;
+pub use cpu::{Cpu, MemoryWrite, Registers, StepOutcome, StopReason, Watchpoint, WatchpointHit};
+
+pub mod trace;
+pub use trace::Trace;
+
+pub mod io;
+pub use io::{IoBus, LoggingIoBus};
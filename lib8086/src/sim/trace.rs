@@ -0,0 +1,103 @@
+use std::fmt;
+
+use crate::code::Width;
+use crate::sim::{Registers, StepOutcome};
+
+/// The flags shown in a trace, with their bit position in the FLAGS word.
+const FLAG_LETTERS: [(char, u16); 9] = [
+    ('C', 0),
+    ('P', 2),
+    ('A', 4),
+    ('Z', 6),
+    ('S', 7),
+    ('T', 8),
+    ('I', 9),
+    ('D', 10),
+    ('O', 11),
+];
+
+/// A line of an execution trace: an executed instruction with its address, followed
+/// by the registers, flags and memory it changed.
+///
+/// ```text
+/// 0000:0000  mov cx, 3 ; cx:0x0->0x3 ip:0x0->0x3
+/// 0000:0003  sub cx, 3 ; cx:0x3->0x0 ip:0x3->0x6 flags:->PZ
+/// 0000:0006  push cx ; sp:0x0->0xfffe ip:0x6->0x7 word [0xfffe]:0x0->0x0
+/// ```
+pub struct Trace<'a> {
+    step: &'a StepOutcome,
+    before: &'a Registers,
+    after: &'a Registers,
+}
+
+impl<'a> Trace<'a> {
+    /// Creates the trace of a step, given the registers before and after it.
+    pub fn new(step: &'a StepOutcome, before: &'a Registers, after: &'a Registers) -> Self {
+        Self {
+            step,
+            before,
+            after,
+        }
+    }
+}
+
+impl fmt::Display for Trace<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x}:{:04x}  ", self.step.cs, self.step.ip)?;
+        match &self.step.instruction {
+            Some(instruction) => write!(f, "{instruction}")?,
+            None => write!(f, "(undecodable)")?,
+        }
+
+        let mut changes = Vec::new();
+        for ((name, old), (_, new)) in self.before.named().into_iter().zip(self.after.named()) {
+            if old != new {
+                changes.push(format!("{name}:{old:#x}->{new:#x}"));
+            }
+        }
+        if self.before.flags != self.after.flags {
+            changes.push(format!(
+                "flags:{}->{}",
+                flag_letters(self.before.flags),
+                flag_letters(self.after.flags)
+            ));
+        }
+        for write in &self.step.writes {
+            let width = match write.width {
+                Width::Byte => "byte",
+                Width::Word => "word",
+            };
+            changes.push(format!(
+                "{width} [{:#x}]:{:#x}->{:#x}",
+                write.address, write.old, write.new
+            ));
+        }
+
+        if !changes.is_empty() {
+            write!(f, " ; {}", changes.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the letters of the flags set in a FLAGS word.
+fn flag_letters(word: u16) -> String {
+    FLAG_LETTERS
+        .iter()
+        .filter(|(_, pos)| word & (1 << pos) != 0)
+        .map(|(letter, _)| letter)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_flag_letters() {
+        assert_eq!(flag_letters(0xf002), "");
+        assert_eq!(flag_letters(0xf046), "PZ");
+        assert_eq!(flag_letters(0xffd7), "CPAZSTIDO");
+    }
+}
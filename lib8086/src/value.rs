@@ -1,6 +1,8 @@
 use std::cmp::Ordering;
 use std::fmt;
 
+use crate::code::Width;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Value {
     Byte(i8),
@@ -20,6 +22,15 @@ impl Value {
         Value::Byte(byte as i8)
     }
 
+    /// Creates a value of the specified width from the low bits of a 16-bit integer.
+    #[inline]
+    pub fn from_u16(val: u16, width: Width) -> Self {
+        match width {
+            Width::Byte => Value::Byte(val as u8 as i8),
+            Width::Word => Value::Word(val as i16),
+        }
+    }
+
     /// Returns whether the value is zero.
     #[inline]
     pub fn is_zero(&self) -> bool {
@@ -47,6 +58,13 @@ impl Value {
         }
     }
 
+    /// Returns whether the least significant byte of the value has an even
+    /// number of set bits.
+    #[inline]
+    pub fn parity(&self) -> bool {
+        (self.as_u16() as u8).count_ones().is_multiple_of(2)
+    }
+
     /// Returns the inner value as a 16-bit integer.
     #[inline]
     pub fn as_u16(&self) -> u16 {
@@ -56,17 +74,140 @@ impl Value {
         }
     }
 
+    /// Returns the inner value zero-extended to a 32-bit unsigned integer.
+    #[inline]
+    pub fn as_unsigned(&self) -> u32 {
+        match self {
+            Self::Byte(v) => *v as u8 as u32,
+            Self::Word(v) => *v as u16 as u32,
+        }
+    }
+
+    /// Returns the inner value sign-extended to a 32-bit signed integer.
+    #[inline]
+    pub fn as_signed(&self) -> i32 {
+        match self {
+            Self::Byte(v) => *v as i32,
+            Self::Word(v) => *v as i32,
+        }
+    }
+
+    /// Returns the width of the value.
+    #[inline]
+    pub fn width(&self) -> Width {
+        match self {
+            Self::Byte(_) => Width::Byte,
+            Self::Word(_) => Width::Word,
+        }
+    }
+
+    /// Splits a double-width integer into its low and high halves, each one
+    /// having the specified width.
+    #[inline]
+    fn split(val: u32, width: Width) -> (Value, Value) {
+        match width {
+            Width::Byte => (
+                Self::from_u16(val as u16, width),
+                Self::from_u16((val >> 8) as u16, width),
+            ),
+            Width::Word => (
+                Self::from_u16(val as u16, width),
+                Self::from_u16((val >> 16) as u16, width),
+            ),
+        }
+    }
+
+    /// Joins a high and a low half of the same width into a double-width integer.
+    #[inline]
+    fn join(high: &Value, low: &Value) -> u32 {
+        match low {
+            Self::Byte(_) => (high.as_unsigned() << 8) | low.as_unsigned(),
+            Self::Word(_) => (high.as_unsigned() << 16) | low.as_unsigned(),
+        }
+    }
+
+    /// Multiplies two unsigned values of the same width, returning the low and high
+    /// halves of the double-width product.
+    pub fn widening_mul(&self, other: &Value) -> (Value, Value) {
+        Self::split(self.as_unsigned() * other.as_unsigned(), self.width())
+    }
+
+    /// Multiplies two signed values of the same width, returning the low and high
+    /// halves of the double-width product.
+    pub fn widening_imul(&self, other: &Value) -> (Value, Value) {
+        Self::split((self.as_signed() * other.as_signed()) as u32, self.width())
+    }
+
+    /// Divides the unsigned double-width integer formed by `high` and `low` by the
+    /// divisor, returning the quotient and the remainder.
+    ///
+    /// Returns `None` if the divisor is zero or the quotient does not fit in the
+    /// width of the divisor.
+    pub fn checked_div(high: &Value, low: &Value, divisor: &Value) -> Option<(Value, Value)> {
+        let dividend = Self::join(high, low);
+        let divisor_val = divisor.as_unsigned();
+        let quotient = dividend.checked_div(divisor_val)?;
+        let max = match divisor.width() {
+            Width::Byte => u8::MAX as u32,
+            Width::Word => u16::MAX as u32,
+        };
+        if quotient > max {
+            return None;
+        }
+        let remainder = dividend % divisor_val;
+        let width = divisor.width();
+        Some((
+            Self::from_u16(quotient as u16, width),
+            Self::from_u16(remainder as u16, width),
+        ))
+    }
+
+    /// Divides the signed double-width integer formed by `high` and `low` by the
+    /// divisor, returning the quotient and the remainder. The quotient is truncated
+    /// towards zero and the remainder has the sign of the dividend.
+    ///
+    /// Returns `None` if the divisor is zero or the quotient does not fit in the
+    /// width of the divisor. As on the 8086, the most negative value, 0x80 or
+    /// 0x8000, is not a valid quotient.
+    pub fn checked_idiv(high: &Value, low: &Value, divisor: &Value) -> Option<(Value, Value)> {
+        let dividend = match low {
+            Self::Byte(_) => Self::join(high, low) as u16 as i16 as i32,
+            Self::Word(_) => Self::join(high, low) as i32,
+        };
+        let divisor_val = divisor.as_signed();
+        let quotient = dividend.checked_div(divisor_val)?;
+        let max = match divisor.width() {
+            Width::Byte => i8::MAX as i32,
+            Width::Word => i16::MAX as i32,
+        };
+        if quotient > max || quotient < -max {
+            return None;
+        }
+        let remainder = dividend % divisor_val;
+        let width = divisor.width();
+        Some((
+            Self::from_u16(quotient as u16, width),
+            Self::from_u16(remainder as u16, width),
+        ))
+    }
+
     /// Adds two values returning the result along with the overflow, carry and
     /// auxiliary carry flags.
     pub fn flagged_add(&self, other: &Value) -> (Value, bool, bool, bool) {
         match (self, other) {
+            (Self::Byte(v1), Self::Byte(v2)) => {
+                let (val, ov) = (*v1).overflowing_add(*v2);
+                let (_, carry) = (*v1 as u8).overflowing_add(*v2 as u8);
+                let aux_carry = (*v1 & 0xF) + (*v2 & 0xF) > 0xF;
+                (Self::Byte(val), ov, carry, aux_carry)
+            }
             (Self::Word(v1), Self::Word(v2)) => {
                 let (val, ov) = (*v1).overflowing_add(*v2);
                 let (_, carry) = (*v1 as u16).overflowing_add(*v2 as u16);
                 let aux_carry = (*v1 & 0xF) + (*v2 & 0xF) > 0xF;
                 (Self::Word(val), ov, carry, aux_carry)
             }
-            _ => panic!("Flagged add has been implemented only for word-word."),
+            _ => panic!("Flagged add requires values of the same width."),
         }
     }
 
@@ -74,49 +215,101 @@ impl Value {
     /// auxiliary carry flags.
     pub fn flagged_sub(&self, other: &Value) -> (Value, bool, bool, bool) {
         match (self, other) {
+            (Self::Byte(v1), Self::Byte(v2)) => {
+                let (val, ov) = (*v1).overflowing_sub(*v2);
+                let carry = (*v1 as u8) < (*v2 as u8);
+                let aux_carry = (*v1 & 0xF) < (*v2 & 0xF);
+                (Self::Byte(val), ov, carry, aux_carry)
+            }
             (Self::Word(v1), Self::Word(v2)) => {
                 let (val, ov) = (*v1).overflowing_sub(*v2);
                 let carry = (*v1 as u16) < (*v2 as u16);
                 let aux_carry = (*v1 & 0xF) < (*v2 & 0xF);
                 (Self::Word(val), ov, carry, aux_carry)
             }
-            _ => panic!("Flagged sub has been implemented only for word-word."),
+            _ => panic!("Flagged sub requires values of the same width."),
         }
     }
 
     /// Performes a carrying add between two values returning the result and
     /// the oveflow, carry and auxiliary flags.
     pub fn flagged_carrying_add(&self, other: &Value, carry: bool) -> (Value, bool, bool, bool) {
-        let carry = if carry {
-            Value::Word(1i16)
-        } else {
-            Value::Word(0i16)
-        };
-        match (self, other) {
-            (Self::Word(v1), Self::Word(v2)) => {
-                let (v1, ov1, cf1, acf1) = self.flagged_add(other);
-                let (v2, ov2, cf2, acf2) = v1.flagged_add(&carry);
-                todo!()
-            }
-            _ => panic!("Flagged add has been implemented only for word-word."),
-        }
+        let (max, min, umax) = Self::carrying_bounds(self, other);
+        let carry = carry as i32;
+
+        let sum = self.as_unsigned() as i32 + other.as_unsigned() as i32 + carry;
+        let signed_sum = self.as_signed() + other.as_signed() + carry;
+        let aux_carry =
+            (self.as_unsigned() & 0xF) as i32 + (other.as_unsigned() & 0xF) as i32 + carry > 0xF;
+        (
+            Self::from_u16(sum as u16, self.width()),
+            signed_sum > max || signed_sum < min,
+            sum > umax,
+            aux_carry,
+        )
     }
 
     /// Performes a carrying sub between two values returning the result and
     /// the oveflow, carry and auxiliary flags.
     pub fn flagged_carrying_sub(&self, other: &Value, carry: bool) -> (Value, bool, bool, bool) {
-        let carry = if carry {
-            Value::Word(1i16)
-        } else {
-            Value::Word(0i16)
-        };
+        let (max, min, _) = Self::carrying_bounds(self, other);
+        let carry = carry as i32;
+
+        let diff = self.as_unsigned() as i32 - other.as_unsigned() as i32 - carry;
+        let signed_diff = self.as_signed() - other.as_signed() - carry;
+        let aux_carry =
+            ((self.as_unsigned() & 0xF) as i32) < (other.as_unsigned() & 0xF) as i32 + carry;
+        (
+            Self::from_u16(diff as u16, self.width()),
+            signed_diff > max || signed_diff < min,
+            diff < 0,
+            aux_carry,
+        )
+    }
+
+    /// Returns the signed maximum, signed minimum and unsigned maximum of the width
+    /// shared by two values.
+    fn carrying_bounds(v1: &Value, v2: &Value) -> (i32, i32, i32) {
+        match (v1, v2) {
+            (Self::Byte(_), Self::Byte(_)) => (i8::MAX as i32, i8::MIN as i32, u8::MAX as i32),
+            (Self::Word(_), Self::Word(_)) => (i16::MAX as i32, i16::MIN as i32, u16::MAX as i32),
+            _ => panic!("Flagged carrying operations require values of the same width."),
+        }
+    }
+
+    /// Applies a bitwise operation to two values of the same width.
+    fn bitwise(&self, other: &Value, f: fn(i16, i16) -> i16) -> Value {
         match (self, other) {
-            (Self::Word(v1), Self::Word(v2)) => {
-                let (v1, ov1, cf1, acf1) = self.flagged_sub(other);
-                let (v2, ov2, cf2, acf2) = v1.flagged_sub(&carry);
-                todo!()
-            }
-            _ => panic!("Flagged carrying add has been implemented only for word-word."),
+            (Self::Byte(v1), Self::Byte(v2)) => Self::Byte(f(*v1 as i16, *v2 as i16) as i8),
+            (Self::Word(v1), Self::Word(v2)) => Self::Word(f(*v1, *v2)),
+            _ => panic!("Bitwise operations require values of the same width."),
+        }
+    }
+
+    /// Returns the bitwise AND of two values.
+    #[inline]
+    pub fn and(&self, other: &Value) -> Value {
+        self.bitwise(other, |v1, v2| v1 & v2)
+    }
+
+    /// Returns the bitwise inclusive OR of two values.
+    #[inline]
+    pub fn or(&self, other: &Value) -> Value {
+        self.bitwise(other, |v1, v2| v1 | v2)
+    }
+
+    /// Returns the bitwise exclusive OR of two values.
+    #[inline]
+    pub fn xor(&self, other: &Value) -> Value {
+        self.bitwise(other, |v1, v2| v1 ^ v2)
+    }
+
+    /// Returns the one's complement of the value.
+    #[inline]
+    pub fn not(&self) -> Value {
+        match self {
+            Self::Byte(v) => Self::Byte(!*v),
+            Self::Word(v) => Self::Word(!*v),
         }
     }
 
@@ -144,3 +337,69 @@ impl fmt::Display for Value {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn test_widening_mul() {
+        let (low, high) = Value::Word(300).widening_mul(&Value::Word(300));
+        assert_eq!(low, Value::Word(0x5f90));
+        assert_eq!(high, Value::Word(0x0001));
+
+        let (low, high) = Value::Byte(-3).widening_imul(&Value::Byte(5));
+        assert_eq!(low, Value::Byte(-15));
+        assert_eq!(high, Value::Byte(-1));
+    }
+
+    #[test]
+    fn test_checked_div() {
+        let (quot, rem) = Value::checked_div(&Value::Byte(1), &Value::Byte(4), &Value::Byte(10))
+            .expect("260 / 10 fits in a byte");
+        assert_eq!(quot, Value::Byte(26));
+        assert_eq!(rem, Value::Byte(0));
+
+        assert!(Value::checked_div(&Value::Word(0), &Value::Word(1), &Value::Word(0)).is_none());
+        assert!(Value::checked_div(&Value::Byte(1), &Value::Byte(0), &Value::Byte(1)).is_none());
+    }
+
+    #[test]
+    fn test_checked_idiv() {
+        let (quot, rem) = Value::checked_idiv(&Value::Word(-1), &Value::Word(-7), &Value::Word(2))
+            .expect("-7 / 2 fits in a word");
+        assert_eq!(quot, Value::Word(-3));
+        assert_eq!(rem, Value::Word(-1));
+
+        // -128 is not a valid byte quotient on the 8086.
+        assert!(
+            Value::checked_idiv(&Value::Byte(0), &Value::Byte(-128), &Value::Byte(-1)).is_none()
+        );
+        assert!(Value::checked_idiv(&Value::Byte(-1), &Value::Byte(0), &Value::Byte(1)).is_none());
+    }
+
+    #[test]
+    fn test_flagged_carrying_add() {
+        let (val, ov, cf, af) = Value::Word(-1).flagged_carrying_add(&Value::Word(0), true);
+        assert_eq!((val, ov, cf, af), (Value::Word(0), false, true, true));
+
+        let (val, ov, cf, af) = Value::Byte(0x7f).flagged_carrying_add(&Value::Byte(0), true);
+        assert_eq!((val, ov, cf, af), (Value::Byte(-128), true, false, true));
+
+        let (val, ov, cf, af) = Value::Byte(0x10).flagged_carrying_add(&Value::Byte(0x21), false);
+        assert_eq!((val, ov, cf, af), (Value::Byte(0x31), false, false, false));
+    }
+
+    #[test]
+    fn test_flagged_carrying_sub() {
+        let (val, ov, cf, af) = Value::Word(0).flagged_carrying_sub(&Value::Word(0), true);
+        assert_eq!((val, ov, cf, af), (Value::Word(-1), false, true, true));
+
+        let (val, ov, cf, af) = Value::Byte(-128).flagged_carrying_sub(&Value::Byte(0), true);
+        assert_eq!((val, ov, cf, af), (Value::Byte(0x7f), true, false, true));
+
+        let (val, ov, cf, af) = Value::Byte(0x31).flagged_carrying_sub(&Value::Byte(0x10), true);
+        assert_eq!((val, ov, cf, af), (Value::Byte(0x20), false, false, false));
+    }
+}
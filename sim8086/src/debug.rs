@@ -0,0 +1,371 @@
+use std::io::{self, BufRead, Write};
+
+use lib8086::code::ops::InterruptOp;
+use lib8086::code::Instruction;
+use lib8086::{Cpu, StepOutcome, StopReason, Trace, Watchpoint};
+
+const PROMPT: &str = "(sim8086) ";
+
+/// The number of bytes shown by a memory dump when no length is given.
+const DUMP_LEN: usize = 0x40;
+
+/// The number of instructions after `IP` shown by a disassembly when no count is given.
+const DISASM_LEN: usize = 5;
+
+/// The number of previously executed instructions shown before `IP` by a disassembly.
+const DISASM_HISTORY: usize = 3;
+
+const HELP: &str = "\
+step [n]             execute the next n instructions, 1 by default (s)
+next                 execute the next instruction, stepping over calls and interrupts (n)
+continue             execute until a breakpoint, watchpoint, halt or error (c)
+reverse-step [n]     undo the last n executed instructions, 1 by default (rs)
+reverse-continue     undo instructions until a breakpoint or watchpoint (rc)
+break [addr]         set a breakpoint, or list them without an address (b)
+delete <addr>        remove a breakpoint
+watch <addr> [len]   stop on writes to len bytes of memory, 1 by default
+unwatch <addr> [len] remove a watchpoint
+regs                 show the registers (r)
+flags                show the flags (f)
+mem <addr> [len]     dump len bytes of memory, 0x40 by default (x)
+disasm [n]           disassemble around IP, showing the next n instructions (d)
+set <reg> <value>    set a register: ax..di, es, cs, ss, ds, ip or flags
+set [addr] <byte>..  write bytes to memory
+help                 show this help (h)
+quit                 exit the debugger (q)
+
+Numbers are hexadecimal. An address is either `segment:offset` or an offset, within
+CS for breakpoints and within DS for memory.";
+
+/// A line-oriented debugger for a program loaded in a CPU.
+pub struct Debugger {
+    cpu: Cpu,
+}
+
+impl Debugger {
+    /// Creates a debugger for the program loaded in the CPU.
+    pub fn new(cpu: Cpu) -> Self {
+        Self { cpu }
+    }
+
+    /// Reads and executes commands until `quit` or the end of the input.
+    ///
+    /// If `echo` is set, each command is written after the prompt, so that the
+    /// output of a script reads like an interactive session.
+    pub fn run(
+        &mut self,
+        input: impl BufRead,
+        mut output: impl Write,
+        echo: bool,
+    ) -> io::Result<()> {
+        let mut lines = input.lines();
+        loop {
+            write!(output, "{PROMPT}")?;
+            output.flush()?;
+            let Some(line) = lines.next() else {
+                writeln!(output)?;
+                return Ok(());
+            };
+            let line = line?;
+            if echo {
+                writeln!(output, "{line}")?;
+            }
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if matches!(line, "quit" | "q") {
+                return Ok(());
+            }
+            match self.command(line) {
+                Ok(text) if text.is_empty() => {}
+                Ok(text) => writeln!(output, "{text}")?,
+                Err(err) => writeln!(output, "error: {err}")?,
+            }
+        }
+    }
+
+    /// Executes a command, returning its output.
+    fn command(&mut self, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+        match (name, args.as_slice()) {
+            ("step" | "s", []) => Ok(self.step(1)),
+            ("step" | "s", [n]) => Ok(self.step(parse_number(n)? as usize)),
+            ("next" | "n", []) => Ok(self.next()),
+            ("continue" | "c", []) => Ok(self.cont()),
+            ("reverse-step" | "rs", []) => self.reverse_step(1),
+            ("reverse-step" | "rs", [n]) => self.reverse_step(parse_number(n)? as usize),
+            ("reverse-continue" | "rc", []) => Ok(self.reverse_cont()),
+            ("break" | "b", []) => Ok(self.list_breakpoints()),
+            ("break" | "b", [addr]) => {
+                let (cs, ip) = self.parse_address(addr, self.cpu.registers().cs)?;
+                self.cpu.add_breakpoint(cs, ip);
+                Ok(format!("breakpoint at {cs:04x}:{ip:04x}"))
+            }
+            ("delete", [addr]) => {
+                let (cs, ip) = self.parse_address(addr, self.cpu.registers().cs)?;
+                if self.cpu.remove_breakpoint(cs, ip) {
+                    Ok(String::new())
+                } else {
+                    Err(format!("no breakpoint at {cs:04x}:{ip:04x}"))
+                }
+            }
+            ("watch", [addr]) => self.watch(addr, 1, true),
+            ("watch", [addr, len]) => self.watch(addr, parse_number(len)? as usize, true),
+            ("unwatch", [addr]) => self.watch(addr, 1, false),
+            ("unwatch", [addr, len]) => self.watch(addr, parse_number(len)? as usize, false),
+            ("regs" | "r", []) => Ok(format!(
+                "{}{}- IP: 0x{:04x}",
+                self.cpu.gen_regs, self.cpu.seg_regs, self.cpu.ip
+            )),
+            ("flags" | "f", []) => Ok(self.cpu.flags.to_string().trim_end().to_string()),
+            ("mem" | "x", [addr]) => self.dump(addr, DUMP_LEN),
+            ("mem" | "x", [addr, len]) => self.dump(addr, parse_number(len)? as usize),
+            ("disasm" | "d", []) => Ok(self.disasm(DISASM_LEN)),
+            ("disasm" | "d", [n]) => Ok(self.disasm(parse_number(n)? as usize)),
+            ("set", [target, values @ ..]) if target.starts_with('[') => {
+                self.set_memory(target, values)
+            }
+            ("set", [reg, value]) => {
+                let value = parse_number(value)?;
+                let mut registers = self.cpu.registers();
+                let slot = registers
+                    .named_mut(reg)
+                    .ok_or_else(|| format!("unknown register `{reg}`"))?;
+                *slot = value;
+                self.cpu.set_registers(&registers);
+                Ok(String::new())
+            }
+            ("help" | "h", []) => Ok(HELP.to_string()),
+            _ => Err(format!("invalid command `{line}`, type `help` for a list")),
+        }
+    }
+
+    /// Executes up to `n` instructions, stopping early if execution stops.
+    fn step(&mut self, n: usize) -> String {
+        let mut lines = Vec::new();
+        for _ in 0..n {
+            let before = self.cpu.registers();
+            let step = self.cpu.step();
+            let after = self.cpu.registers();
+            if step.instruction.is_some() && step.stop != Some(StopReason::Halt) {
+                lines.push(Trace::new(&step, &before, &after).to_string());
+            }
+            if let Some(stop) = self.describe_stop(&step) {
+                lines.push(stop);
+                break;
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Executes the next instruction, running calls and software interrupts until
+    /// they return.
+    fn next(&mut self) -> String {
+        let registers = self.cpu.registers();
+        let (cs, ip) = (registers.cs, registers.ip);
+        let ret = match self.cpu.instruction_at(cs, ip) {
+            Ok((Instruction::Call(_), size)) => ip.wrapping_add(size as u16),
+            Ok((Instruction::Interrupt(op), size)) if !matches!(op, InterruptOp::Iret) => {
+                ip.wrapping_add(size as u16)
+            }
+            _ => return self.step(1),
+        };
+
+        let temporary = !self.cpu.breakpoints().contains(&(cs, ret));
+        self.cpu.add_breakpoint(cs, ret);
+        let step = self.cpu.run();
+        if temporary {
+            self.cpu.remove_breakpoint(cs, ret);
+        }
+
+        let registers = self.cpu.registers();
+        let returned = (registers.cs, registers.ip) == (cs, ret);
+        match step.stop {
+            Some(StopReason::Breakpoint) if temporary && returned => {
+                format!("=> {}", self.current())
+            }
+            _ => self.describe_stop(&step).unwrap_or_default(),
+        }
+    }
+
+    /// Executes until a breakpoint, a watchpoint, a halt or an error.
+    fn cont(&mut self) -> String {
+        let step = self.cpu.run();
+        self.describe_stop(&step).unwrap_or_default()
+    }
+
+    /// Undoes up to `n` executed instructions, stopping early if there are no more.
+    fn reverse_step(&mut self, n: usize) -> Result<String, String> {
+        for i in 0..n {
+            if self.cpu.step_back().is_none() {
+                if i == 0 {
+                    return Err("no executed instruction to undo".to_string());
+                }
+                break;
+            }
+        }
+        Ok(format!("=> {}", self.current()))
+    }
+
+    /// Undoes instructions until a breakpoint or a watchpoint.
+    fn reverse_cont(&mut self) -> String {
+        let reason = match self.cpu.run_back() {
+            Some(StopReason::Watchpoint(_)) => "stopped at watchpoint",
+            Some(_) => "stopped at breakpoint",
+            None => "reached the oldest instruction that can be undone",
+        };
+        format!("{reason}\n=> {}", self.current())
+    }
+
+    /// Describes why execution stopped after a step, if it did.
+    fn describe_stop(&self, step: &StepOutcome) -> Option<String> {
+        let reason = match step.stop.as_ref()? {
+            StopReason::Halt => return Some(format!("halted at {:04x}:{:04x}", step.cs, step.ip)),
+            StopReason::Error(err) => return Some(format!("error: {err}")),
+            StopReason::Interrupt(_) => return None,
+            StopReason::Breakpoint => "breakpoint",
+            StopReason::Watchpoint(_) => "watchpoint",
+        };
+        Some(format!("stopped at {reason}\n=> {}", self.current()))
+    }
+
+    /// Returns the address and the disassembly of the instruction at `CS:IP`.
+    fn current(&self) -> String {
+        let registers = self.cpu.registers();
+        self.location(registers.cs, registers.ip)
+    }
+
+    /// Returns the address and the disassembly of the instruction at an address.
+    fn location(&self, cs: u16, ip: u16) -> String {
+        match self.cpu.instruction_at(cs, ip) {
+            Ok((Instruction::Halt, _)) => format!("{cs:04x}:{ip:04x}  hlt"),
+            Ok((instruction, _)) => format!("{cs:04x}:{ip:04x}  {instruction}"),
+            Err(_) => format!("{cs:04x}:{ip:04x}  (undecodable)"),
+        }
+    }
+
+    /// Adds or removes a watchpoint on writes to `len` bytes of memory.
+    fn watch(&mut self, addr: &str, len: usize, add: bool) -> Result<String, String> {
+        let (segment, offset) = self.parse_address(addr, self.cpu.registers().ds)?;
+        let start = self.cpu.physical_address_of(segment, offset);
+        let watchpoint = Watchpoint::Write(start..start + len);
+        if add {
+            self.cpu.add_watchpoint(watchpoint);
+            Ok(format!(
+                "watchpoint on writes to {start:05x}..{:05x}",
+                start + len
+            ))
+        } else if self.cpu.remove_watchpoint(&watchpoint) {
+            Ok(String::new())
+        } else {
+            Err(format!("no watchpoint on {start:05x}..{:05x}", start + len))
+        }
+    }
+
+    /// Lists the breakpoints.
+    fn list_breakpoints(&self) -> String {
+        let breakpoints = self.cpu.breakpoints();
+        if breakpoints.is_empty() {
+            return "no breakpoints".to_string();
+        }
+        breakpoints
+            .iter()
+            .map(|&(cs, ip)| self.location(cs, ip))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Disassembles the last executed instructions, the instruction at `CS:IP`
+    /// and the `n - 1` instructions after it.
+    fn disasm(&self, n: usize) -> String {
+        let registers = self.cpu.registers();
+        let (cs, mut ip) = (registers.cs, registers.ip);
+
+        let mut history: Vec<(u16, u16)> = self.cpu.history().collect();
+        history.dedup();
+        history.retain(|&addr| addr != (cs, ip));
+        let skip = history.len().saturating_sub(DISASM_HISTORY);
+
+        let mut lines: Vec<String> = history[skip..]
+            .iter()
+            .map(|&(cs, ip)| format!("   {}", self.location(cs, ip)))
+            .collect();
+        for i in 0..n {
+            let marker = if i == 0 { "=>" } else { "  " };
+            lines.push(format!("{marker} {}", self.location(cs, ip)));
+            match self.cpu.instruction_at(cs, ip) {
+                Ok((Instruction::Halt, _)) | Err(_) => break,
+                Ok((_, size)) => ip = ip.wrapping_add(size as u16),
+            }
+        }
+        lines.join("\n")
+    }
+
+    /// Dumps `len` bytes of memory, 16 per line, with their ASCII representation.
+    fn dump(&self, addr: &str, len: usize) -> Result<String, String> {
+        let (segment, offset) = self.parse_address(addr, self.cpu.registers().ds)?;
+        let start = self.cpu.physical_address_of(segment, offset);
+
+        let mut lines = Vec::new();
+        for line_start in (0..len).step_by(16) {
+            let addrs =
+                (line_start..len.min(line_start + 16)).map(|i| (start + i) % self.cpu.mem.len());
+            let bytes: Vec<u8> = addrs.map(|idx| self.cpu.mem[idx]).collect();
+            let hex: Vec<String> = bytes.iter().map(|b| format!("{b:02x}")).collect();
+            let ascii: String = bytes
+                .iter()
+                .map(|&b| match b {
+                    0x20..=0x7e => b as char,
+                    _ => '.',
+                })
+                .collect();
+            let idx = (start + line_start) % self.cpu.mem.len();
+            lines.push(format!("{idx:05x}  {:<47}  {ascii}", hex.join(" ")));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    /// Writes bytes to memory at an address written as `[addr]`.
+    fn set_memory(&mut self, target: &str, values: &[&str]) -> Result<String, String> {
+        let addr = target
+            .strip_prefix('[')
+            .and_then(|addr| addr.strip_suffix(']'))
+            .ok_or_else(|| format!("invalid memory address `{target}`"))?;
+        if values.is_empty() {
+            return Err("no bytes to write".to_string());
+        }
+        let bytes = values
+            .iter()
+            .map(|value| {
+                u8::try_from(parse_number(value)?).map_err(|_| format!("`{value}` is not a byte"))
+            })
+            .collect::<Result<Vec<u8>, String>>()?;
+
+        let (segment, offset) = self.parse_address(addr, self.cpu.registers().ds)?;
+        let start = self.cpu.physical_address_of(segment, offset);
+        for (i, byte) in bytes.into_iter().enumerate() {
+            let idx = (start + i) % self.cpu.mem.len();
+            self.cpu.mem[idx] = byte;
+        }
+        Ok(String::new())
+    }
+
+    /// Parses an address written as `segment:offset`, or as an offset within the
+    /// specified segment.
+    fn parse_address(&self, addr: &str, segment: u16) -> Result<(u16, u16), String> {
+        match addr.split_once(':') {
+            Some((segment, offset)) => Ok((parse_number(segment)?, parse_number(offset)?)),
+            None => Ok((segment, parse_number(addr)?)),
+        }
+    }
+}
+
+/// Parses a hexadecimal 16-bit number, with or without a `0x` prefix.
+fn parse_number(text: &str) -> Result<u16, String> {
+    let digits = text.strip_prefix("0x").unwrap_or(text);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid number `{text}`"))
+}
@@ -0,0 +1,407 @@
+use std::io::{self, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::ops::Range;
+
+use lib8086::{Cpu, StopReason, Watchpoint, WatchpointHit};
+
+/// The registers in the order of the i386 register file, which GDB also uses for
+/// the 8086 with `set architecture i8086`. Each register is sent as 32 bits, and
+/// the registers that the 8086 does not have, `fs` and `gs`, read as zero.
+const REGISTERS: [&str; 16] = [
+    "ax", "cx", "dx", "bx", "sp", "bp", "si", "di", "ip", "flags", "cs", "ss", "ds", "es", "fs",
+    "gs",
+];
+
+/// The number of instructions executed by `c` between checks for an interrupt
+/// request from the client.
+const INTERRUPT_CHECK: usize = 4096;
+
+/// The byte sent by the client to interrupt a running program.
+const INTERRUPT: u8 = 0x03;
+
+/// The largest number of bytes of memory read or written by a packet. Each byte
+/// takes two hexadecimal digits, so that they fit in the packet size.
+const MAX_ACCESS_LEN: usize = 0x2000;
+
+const SIGINT: u8 = 2;
+const SIGILL: u8 = 4;
+const SIGTRAP: u8 = 5;
+
+/// A packet received from a GDB client.
+enum Packet {
+    /// The payload of a `$payload#checksum` packet.
+    Command(String),
+    /// A request to interrupt the running program.
+    Interrupt,
+}
+
+/// A GDB Remote Serial Protocol connection.
+struct Connection {
+    reader: BufReader<TcpStream>,
+    stream: TcpStream,
+    /// The last packet sent, which is sent again if the client rejects it.
+    sent: Vec<u8>,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        // Packets are small and each waits for a reply.
+        stream.set_nodelay(true)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        Ok(Self {
+            reader,
+            stream,
+            sent: Vec::new(),
+        })
+    }
+
+    /// Returns the next packet, acknowledging it, or `None` once the client has
+    /// disconnected.
+    fn read_packet(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            let Some(byte) = self.read_byte()? else {
+                return Ok(None);
+            };
+            match byte {
+                b'$' => {}
+                INTERRUPT => return Ok(Some(Packet::Interrupt)),
+                b'-' => {
+                    self.stream.write_all(&self.sent)?;
+                    continue;
+                }
+                // Acknowledgements and anything outside a packet.
+                _ => continue,
+            }
+
+            let mut payload = Vec::new();
+            loop {
+                match self.read_byte()? {
+                    Some(b'#') => break,
+                    Some(byte) => payload.push(byte),
+                    None => return Ok(None),
+                }
+            }
+            let mut checksum = [0u8; 2];
+            self.reader.read_exact(&mut checksum)?;
+
+            let valid = std::str::from_utf8(&checksum)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                == Some(checksum_of(&payload));
+            if valid {
+                self.stream.write_all(b"+")?;
+                return Ok(Some(Packet::Command(
+                    String::from_utf8_lossy(&payload).into_owned(),
+                )));
+            }
+            self.stream.write_all(b"-")?;
+        }
+    }
+
+    /// Sends a packet with the specified payload.
+    fn send(&mut self, payload: &str) -> io::Result<()> {
+        let checksum = checksum_of(payload.as_bytes());
+        self.sent = format!("${payload}#{checksum:02x}").into_bytes();
+        self.stream.write_all(&self.sent)?;
+        self.stream.flush()
+    }
+
+    /// Returns whether the client requested an interrupt, without blocking.
+    fn interrupted(&mut self) -> io::Result<bool> {
+        if !self.reader.buffer().is_empty() {
+            return Ok(self.reader.buffer().contains(&INTERRUPT));
+        }
+        self.stream.set_nonblocking(true)?;
+        let mut byte = [0u8; 1];
+        let result = self.stream.peek(&mut byte);
+        self.stream.set_nonblocking(false)?;
+        match result {
+            Ok(1) => Ok(byte[0] == INTERRUPT),
+            Ok(_) => Ok(false),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn read_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0u8; 1];
+        match self.reader.read(&mut byte)? {
+            0 => Ok(None),
+            _ => Ok(Some(byte[0])),
+        }
+    }
+}
+
+/// A GDB Remote Serial Protocol stub for a program loaded in a CPU.
+pub struct GdbServer {
+    cpu: Cpu,
+    /// Whether the client reports breakpoint stops with `swbreak`, so that it does
+    /// not adjust `IP` after them.
+    swbreak: bool,
+    /// The reply to `?`: the reason of the last stop.
+    last_stop: String,
+}
+
+impl GdbServer {
+    /// Creates a stub for the program loaded in the CPU.
+    pub fn new(cpu: Cpu) -> Self {
+        Self {
+            cpu,
+            swbreak: false,
+            last_stop: format!("S{SIGTRAP:02x}"),
+        }
+    }
+
+    /// Serves a client until it disconnects, detaches or kills the program.
+    pub fn serve(&mut self, stream: TcpStream) -> io::Result<()> {
+        let mut conn = Connection::new(stream)?;
+        while let Some(packet) = conn.read_packet()? {
+            let Packet::Command(command) = packet else {
+                // The program is stopped already.
+                continue;
+            };
+            match command.as_str() {
+                "k" => return Ok(()),
+                "D" => {
+                    conn.send("OK")?;
+                    return Ok(());
+                }
+                _ => {
+                    let reply = match command.strip_prefix('c') {
+                        Some(address) => match self.resume_at(address) {
+                            Some(()) => self.cont(&mut conn)?,
+                            None => "E01".to_string(),
+                        },
+                        None => self.command(&command),
+                    };
+                    conn.send(&reply)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes a packet that does not resume the program, returning the reply.
+    ///
+    /// Unsupported packets get an empty reply.
+    fn command(&mut self, command: &str) -> String {
+        let (name, args) = command.split_at(command.chars().next().map_or(0, char::len_utf8));
+        let reply = match name {
+            "?" => Some(self.last_stop.clone()),
+            "g" => Some(self.read_registers()),
+            "G" => self.write_registers(args),
+            "p" => self.read_register(args),
+            "P" => self.write_register(args),
+            "m" => self.read_memory(args),
+            "M" => self.write_memory(args),
+            "s" => self.resume_at(args).map(|()| self.step()),
+            "Z" => self.set_point(args, true),
+            "z" => self.set_point(args, false),
+            "H" => Some("OK".to_string()),
+            "q" => self.query(args),
+            _ => Some(String::new()),
+        };
+        reply.unwrap_or_else(|| "E01".to_string())
+    }
+
+    /// Replies to a general query.
+    fn query(&mut self, query: &str) -> Option<String> {
+        let reply = if let Some(features) = query.strip_prefix("Supported") {
+            self.swbreak = features.split([':', ';']).any(|f| f == "swbreak+");
+            "PacketSize=4000;swbreak+"
+        } else {
+            match query {
+                "Attached" => "1",
+                "C" => "QC1",
+                "fThreadInfo" => "m1",
+                "sThreadInfo" => "l",
+                "Symbol::" => "OK",
+                _ => "",
+            }
+        };
+        Some(reply.to_string())
+    }
+
+    /// Sets `IP` to the hexadecimal address of `c` or `s` data, if any, before the
+    /// program resumes.
+    fn resume_at(&mut self, address: &str) -> Option<()> {
+        if !address.is_empty() {
+            let mut registers = self.cpu.registers();
+            *registers.named_mut("ip")? = u16::from_str_radix(address, 16).ok()?;
+            self.cpu.set_registers(&registers);
+        }
+        Some(())
+    }
+
+    /// Executes a single instruction and returns the stop reply.
+    fn step(&mut self) -> String {
+        let step = self.cpu.step();
+        let reply = self
+            .stop_reply(step.stop)
+            .unwrap_or(format!("S{SIGTRAP:02x}"));
+        self.last_stop.clone_from(&reply);
+        reply
+    }
+
+    /// Executes until the program stops or the client interrupts it, and returns
+    /// the stop reply.
+    fn cont(&mut self, conn: &mut Connection) -> io::Result<String> {
+        let mut count: usize = 0;
+        let reply = loop {
+            let stop = self.cpu.step().stop;
+            if let Some(reply) = self.stop_reply(stop) {
+                break reply;
+            }
+            count += 1;
+            if count.is_multiple_of(INTERRUPT_CHECK) && conn.interrupted()? {
+                break format!("S{SIGINT:02x}");
+            }
+        };
+        self.last_stop.clone_from(&reply);
+        Ok(reply)
+    }
+
+    /// Returns the stop reply for a stop reason, or `None` if execution goes on.
+    fn stop_reply(&self, stop: Option<StopReason>) -> Option<String> {
+        let reply = match stop? {
+            StopReason::Interrupt(_) => return None,
+            StopReason::Halt => "W00".to_string(),
+            StopReason::Error(_) => format!("S{SIGILL:02x}"),
+            StopReason::Breakpoint if self.swbreak => format!("T{SIGTRAP:02x}swbreak:;"),
+            StopReason::Breakpoint => format!("S{SIGTRAP:02x}"),
+            StopReason::Watchpoint(WatchpointHit::Write(write)) => {
+                format!("T{SIGTRAP:02x}watch:{:x};", write.address)
+            }
+            StopReason::Watchpoint(WatchpointHit::Read { address, .. }) => {
+                format!("T{SIGTRAP:02x}rwatch:{address:x};")
+            }
+            StopReason::Watchpoint(_) => format!("S{SIGTRAP:02x}"),
+        };
+        Some(reply)
+    }
+
+    /// Returns the values of all the registers.
+    fn read_registers(&self) -> String {
+        (0..REGISTERS.len())
+            .map(|n| self.register(n).unwrap_or_default())
+            .collect()
+    }
+
+    /// Sets the values of the registers from `G` data, in order.
+    fn write_registers(&mut self, data: &str) -> Option<String> {
+        if !data.len().is_multiple_of(8) || data.len() > REGISTERS.len() * 8 {
+            return None;
+        }
+        let mut registers = self.cpu.registers();
+        for (n, value) in data.as_bytes().chunks(8).enumerate() {
+            let value = decode_register(std::str::from_utf8(value).ok()?)?;
+            if let Some(reg) = registers.named_mut(REGISTERS[n]) {
+                *reg = value;
+            }
+        }
+        self.cpu.set_registers(&registers);
+        Some("OK".to_string())
+    }
+
+    /// Returns the value of the register with the hexadecimal number in `p` data.
+    fn read_register(&self, args: &str) -> Option<String> {
+        self.register(usize::from_str_radix(args, 16).ok()?)
+    }
+
+    /// Sets the value of a register from `P` data: `number=value`.
+    fn write_register(&mut self, args: &str) -> Option<String> {
+        let (n, value) = args.split_once('=')?;
+        let name = REGISTERS.get(usize::from_str_radix(n, 16).ok()?)?;
+        let value = decode_register(value)?;
+        let mut registers = self.cpu.registers();
+        if let Some(reg) = registers.named_mut(name) {
+            *reg = value;
+        }
+        self.cpu.set_registers(&registers);
+        Some("OK".to_string())
+    }
+
+    /// Returns the value of a register as 32 bits in target byte order.
+    fn register(&self, n: usize) -> Option<String> {
+        let name = REGISTERS.get(n)?;
+        let value = self.cpu.registers().named_mut(name).map_or(0, |reg| *reg);
+        let [low, high] = value.to_le_bytes();
+        Some(format!("{low:02x}{high:02x}0000"))
+    }
+
+    /// Returns the bytes of memory from `m` data: `address,length`.
+    fn read_memory(&self, args: &str) -> Option<String> {
+        let range = parse_range(args, self.cpu.mem.len())?;
+        Some(
+            self.cpu.mem[range]
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect(),
+        )
+    }
+
+    /// Writes bytes to memory from `M` data: `address,length:bytes`.
+    fn write_memory(&mut self, args: &str) -> Option<String> {
+        let (range, data) = args.split_once(':')?;
+        let range = parse_range(range, self.cpu.mem.len())?;
+        if data.len() != range.len() * 2 {
+            return None;
+        }
+        let bytes = (0..range.len())
+            .map(|i| u8::from_str_radix(data.get(i * 2..i * 2 + 2)?, 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        self.cpu.mem[range].copy_from_slice(&bytes);
+        Some("OK".to_string())
+    }
+
+    /// Inserts or removes a breakpoint or watchpoint from `Z` or `z` data:
+    /// `type,address,kind`.
+    ///
+    /// Software breakpoints, write watchpoints and read watchpoints are supported.
+    fn set_point(&mut self, args: &str, insert: bool) -> Option<String> {
+        let (kind, range) = args.split_once(',')?;
+        let range = parse_range(range.split(';').next()?, self.cpu.mem.len())?;
+        let (cs, ip) = ((range.start >> 4) as u16, (range.start & 0xF) as u16);
+        match (kind, insert) {
+            ("0", true) => self.cpu.add_breakpoint(cs, ip),
+            ("0", false) => {
+                self.cpu.remove_breakpoint(cs, ip);
+            }
+            ("2", true) => self.cpu.add_watchpoint(Watchpoint::Write(range)),
+            ("2", false) => {
+                self.cpu.remove_watchpoint(&Watchpoint::Write(range));
+            }
+            ("3", true) => self.cpu.add_watchpoint(Watchpoint::Read(range)),
+            ("3", false) => {
+                self.cpu.remove_watchpoint(&Watchpoint::Read(range));
+            }
+            _ => return Some(String::new()),
+        }
+        Some("OK".to_string())
+    }
+}
+
+/// Returns the checksum of a packet payload: the sum of its bytes modulo 256.
+fn checksum_of(payload: &[u8]) -> u8 {
+    payload.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+/// Parses a register value sent as 32 bits in target byte order, keeping the low
+/// 16 bits.
+fn decode_register(hex: &str) -> Option<u16> {
+    let low = u8::from_str_radix(hex.get(0..2)?, 16).ok()?;
+    let high = u8::from_str_radix(hex.get(2..4)?, 16).ok()?;
+    Some(u16::from_le_bytes([low, high]))
+}
+
+/// Parses a memory range: `address,length`.
+///
+/// Returns `None` unless the range lies within memory of the specified size and
+/// is at most `MAX_ACCESS_LEN` bytes long.
+fn parse_range(args: &str, mem_size: usize) -> Option<Range<usize>> {
+    let (address, len) = args.split_once(',')?;
+    let address = usize::from_str_radix(address, 16).ok()?;
+    let len = usize::from_str_radix(len, 16).ok()?;
+    let end = address.checked_add(len)?;
+    (len <= MAX_ACCESS_LEN && end <= mem_size).then_some(address..end)
+}
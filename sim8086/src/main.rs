@@ -1,10 +1,21 @@
+use std::error::Error;
+use std::fmt;
 use std::fs;
+use std::io;
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 use clap::{Parser, Subcommand};
 
-use lib8086::{Cpu, DecodeError, Decoder};
+mod debug;
+use debug::Debugger;
+
+mod gdb;
+use gdb::GdbServer;
+
+use lib8086::{Cpu, Decoder, ExecutionError, StopReason, Trace};
 
 #[derive(Parser)]
 struct Cli {
@@ -19,55 +30,240 @@ pub enum Command {
 
         #[arg(long, short)]
         output: Option<PathBuf>,
+
+        /// Fail on the first byte that cannot be decoded, instead of emitting it as data.
+        #[arg(long)]
+        strict: bool,
     },
 
     Execute {
-        path: PathBuf,
+        #[arg(required_unless_present = "load_state", conflicts_with = "load_state")]
+        path: Option<PathBuf>,
 
         #[arg(long, short)]
         output: Option<PathBuf>,
+
+        /// Print every executed instruction with the registers, flags and memory it changed.
+        #[arg(long)]
+        trace: bool,
+
+        /// Stop after executing at most this many instructions.
+        #[arg(long)]
+        steps: Option<u64>,
+
+        /// Save a snapshot of the CPU to this file once the execution stops.
+        #[arg(long)]
+        save_state: Option<PathBuf>,
+
+        /// Resume the execution from a snapshot of the CPU instead of a program.
+        #[arg(long)]
+        load_state: Option<PathBuf>,
+    },
+
+    Debug {
+        path: PathBuf,
+
+        /// Read the debugger commands from a file instead of the standard input.
+        #[arg(long)]
+        script: Option<PathBuf>,
+    },
+
+    Gdbserver {
+        path: PathBuf,
+
+        /// The local TCP port to listen on. With 0, a free port is chosen.
+        #[arg(long)]
+        port: u16,
     },
 }
 
-fn main() -> Result<(), DecodeError> {
+fn main() -> ExitCode {
     let cli = Cli::parse();
-    match cli.command {
-        Command::Decode { path, output } => {
-            let buffer = fs::read(path).expect("Failed to read input byte-code file.");
+    match run(cli.command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    match command {
+        Command::Decode {
+            path,
+            output,
+            strict,
+        } => {
+            let buffer = fs::read(&path).map_err(file_error("read", &path))?;
             let decoder = Decoder::new(buffer);
-            let iqueue = decoder.decode()?;
+            let iqueue = match strict {
+                true => decoder.decode()?,
+                false => decoder.decode_lenient(),
+            };
+            let undecoded = iqueue.undecoded();
+            if undecoded > 0 {
+                eprintln!("{undecoded} byte(s) could not be decoded and were emitted as `db`.");
+            }
             let asm = iqueue.to_string();
 
             match output {
                 Some(opath) => {
                     let mut output =
-                        fs::File::create(opath).expect("Failed to create new output file.");
-                    write!(output, "{asm}").expect("Failed to write output.")
+                        fs::File::create(&opath).map_err(file_error("create", &opath))?;
+                    write!(output, "{asm}").map_err(file_error("write", &opath))?
                 }
                 None => println!("{asm}"),
             }
         }
-        Command::Execute { path, output } => {
-            let buffer = fs::read(path).expect("Failed to read input byte-code file.");
-
+        Command::Execute {
+            path,
+            output,
+            trace,
+            steps,
+            save_state,
+            load_state,
+        } => {
             let mut cpu = Cpu::new();
-            cpu.load_instructions(&buffer);
-            cpu.execute().expect("Execution failed");
+            let buffer = match load_state {
+                Some(spath) => {
+                    let file = fs::File::open(&spath).map_err(file_error("open", &spath))?;
+                    cpu.load_snapshot(io::BufReader::new(file))?;
+                    None
+                }
+                None => {
+                    let path = path.ok_or("either a program or a state file is required")?;
+                    let buffer = fs::read(&path).map_err(file_error("read", &path))?;
+                    cpu.load_instructions(&buffer)?;
+                    Some(buffer)
+                }
+            };
 
-            let decoder = Decoder::new(buffer);
-            let iqueue = decoder.decode()?;
+            if trace {
+                println!("TRACE");
+                println!("-------------------");
+            }
+            let result = match (trace, steps) {
+                (false, None) => cpu.execute(),
+                _ => execute_steps(&mut cpu, trace, steps),
+            };
+            if let Err(err) = result {
+                return Err(CrashReport::new(&cpu, err).into());
+            }
+
+            if let (false, Some(buffer)) = (trace, buffer) {
+                let decoder = Decoder::new(buffer);
+                let iqueue = decoder.decode_lenient();
 
-            println!("\nINSTRUCTIONS");
-            println!("-------------------");
-            println!("{iqueue}");
+                println!("\nINSTRUCTIONS");
+                println!("-------------------");
+                println!("{iqueue}");
+            }
             println!("{cpu}");
 
+            if let Some(spath) = save_state {
+                let file = fs::File::create(&spath).map_err(file_error("create", &spath))?;
+                cpu.save_snapshot(io::BufWriter::new(file))?;
+            }
+
             if let Some(opath) = output {
-                let mut file = fs::File::create(opath).expect("Failed to create new output file");
+                let mut file = fs::File::create(&opath).map_err(file_error("create", &opath))?;
                 file.write_all(&cpu.mem)
-                    .expect("Failed to write memory into output file");
+                    .map_err(file_error("write", &opath))?;
+            }
+        }
+        Command::Debug { path, script } => {
+            let buffer = fs::read(&path).map_err(file_error("read", &path))?;
+
+            let mut cpu = Cpu::new();
+            cpu.load_instructions(&buffer)?;
+            let mut debugger = Debugger::new(cpu);
+            match script {
+                Some(spath) => {
+                    let file = fs::File::open(&spath).map_err(file_error("open", &spath))?;
+                    debugger.run(io::BufReader::new(file), io::stdout(), true)?
+                }
+                None => debugger.run(io::stdin().lock(), io::stdout(), false)?,
             }
         }
+        Command::Gdbserver { path, port } => {
+            let buffer = fs::read(&path).map_err(file_error("read", &path))?;
+
+            let mut cpu = Cpu::new();
+            cpu.load_instructions(&buffer)?;
+            let listener = TcpListener::bind(("127.0.0.1", port))?;
+            eprintln!("Listening on {}", listener.local_addr()?);
+            let (stream, _) = listener.accept()?;
+            GdbServer::new(cpu).serve(stream)?;
+        }
     }
     Ok(())
 }
+
+/// Returns a function that describes an I/O error on the file at `path`.
+fn file_error<'a>(action: &'a str, path: &'a Path) -> impl FnOnce(io::Error) -> String + 'a {
+    move |err| format!("failed to {action} `{}`: {err}", path.display())
+}
+
+/// Executes the loaded instructions until the halt, or until `steps` instructions
+/// have been executed. If `trace` is set, the trace of every instruction is printed.
+fn execute_steps(cpu: &mut Cpu, trace: bool, steps: Option<u64>) -> Result<(), ExecutionError> {
+    let mut before = cpu.registers();
+    for _ in 0..steps.unwrap_or(u64::MAX) {
+        let step = cpu.step();
+        match step.stop {
+            Some(StopReason::Error(err)) => return Err(err),
+            Some(StopReason::Halt) => return Ok(()),
+            _ if trace => {
+                let after = cpu.registers();
+                println!("{}", Trace::new(&step, &before, &after));
+                before = after;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// The report of a failed execution, with the most recently executed instructions
+/// and the registers at the time of the failure.
+#[derive(Debug)]
+struct CrashReport {
+    error: ExecutionError,
+    history: Vec<String>,
+}
+
+impl CrashReport {
+    fn new(cpu: &Cpu, error: ExecutionError) -> Self {
+        let history = cpu
+            .history()
+            .map(|(cs, ip)| {
+                let instruction = match cpu.instruction_at(cs, ip) {
+                    Ok((instruction, _)) => instruction.to_string(),
+                    Err(_) => "(undecodable)".to_string(),
+                };
+                format!("{cs:04x}:{ip:04x}  {instruction}")
+            })
+            .collect();
+        Self { error, history }
+    }
+}
+
+impl fmt::Display for CrashReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.error)?;
+        writeln!(f, "\nlast executed instructions:")?;
+        let last = self.history.len().saturating_sub(1);
+        for (i, line) in self.history.iter().enumerate() {
+            let marker = if i == last { "->" } else { "  " };
+            writeln!(f, " {marker} {line}")?;
+        }
+        writeln!(f, "\nregisters:")?;
+        for line in self.error.registers.to_string().lines() {
+            writeln!(f, "    {line}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for CrashReport {}
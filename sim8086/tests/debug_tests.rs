@@ -0,0 +1,32 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Replays the debugger script `<name>.script` on a program and compares the session
+/// with `<name>.out`.
+fn replay_script(name: &str, program: &str) {
+    let manifest_path = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let data_path = manifest_path.join("tests/data/debug");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sim8086"))
+        .arg("debug")
+        .arg("--script")
+        .arg(data_path.join(name).with_extension("script"))
+        .arg(manifest_path.join("tests/data/execute").join(program))
+        .output()
+        .expect("Failed to run `sim8086 debug`.");
+    assert!(output.status.success());
+
+    let expected = std::fs::read_to_string(data_path.join(name).with_extension("out"))
+        .expect("Failed to read expected session.");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+}
+
+#[test]
+fn test_debug_call_ret() {
+    replay_script("call_ret", "call_ret");
+}
+
+#[test]
+fn test_debug_reverse() {
+    replay_script("reverse", "call_ret");
+}
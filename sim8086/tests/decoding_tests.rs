@@ -86,3 +86,48 @@ fn test_decode() {
         .close()
         .expect("Failed to close temporary directory");
 }
+
+#[test]
+fn test_decode_lenient() {
+    let temp_dir = TempDir::new("lenient").expect("Failed to create temporary directory");
+    let bin_path = temp_dir.path().join("mixed");
+    // mov cx, 3; a two-byte data table; inc cx
+    std::fs::write(&bin_path, [0xb9, 0x03, 0x00, 0x60, 0xf1, 0x41])
+        .expect("Failed to write byte-code file.");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sim8086"))
+        .arg("decode")
+        .arg(&bin_path)
+        .output()
+        .expect("Failed to run `sim8086 decode`.");
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(stdout.trim_end(), "mov cx, 3\ndb 0x60\ndb 0xf1\ninc cx");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("2 byte(s) could not be decoded"));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_sim8086"))
+        .arg("decode")
+        .arg("--strict")
+        .arg(&bin_path)
+        .output()
+        .expect("Failed to run `sim8086 decode --strict`.");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(
+        stderr.trim_end(),
+        "error: unknown OPCODE 0b01100000 at offset 0x0003 (bytes: 60)"
+    );
+}
+
+#[test]
+fn test_decode_missing_file() {
+    let output = Command::new(env!("CARGO_BIN_EXE_sim8086"))
+        .arg("decode")
+        .arg("missing")
+        .output()
+        .expect("Failed to run `sim8086 decode`.");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("error: failed to read `missing`: "));
+}
@@ -1,16 +1,57 @@
+use std::cell::RefCell;
 use std::path::Path;
+use std::rc::Rc;
 
-use lib8086::Cpu;
+use lib8086::code::{DecodeErrorKind, Field};
+use lib8086::code::{Register, Width};
+use lib8086::sim::cpu::UNDO_LEN;
+use lib8086::sim::MemoryWrite;
+use lib8086::{
+    Cpu, ExecutionError, ExecutionErrorKind, IoBus, ProgramSizeError, SnapshotError, StopReason,
+    Trace, Watchpoint, WatchpointHit,
+};
 
 fn execute_file(filename: &str) -> Cpu {
+    execute_file_on(filename, Cpu::new())
+}
+
+fn execute_file_on(filename: &str, mut cpu: Cpu) -> Cpu {
+    load_file(filename, &mut cpu);
+    cpu.execute().unwrap();
+    cpu
+}
+
+/// Executes a program that is expected to fail, returning the error.
+fn execute_file_err(filename: &str) -> (Cpu, ExecutionError) {
+    let mut cpu = Cpu::new();
+    load_file(filename, &mut cpu);
+    let err = cpu.execute().unwrap_err();
+    (cpu, err)
+}
+
+fn load_file(filename: &str, cpu: &mut Cpu) {
     let buffer_path = Path::new(env!("CARGO_MANIFEST_DIR"))
         .join("tests/data/execute")
         .join(filename);
     let buffer = std::fs::read(buffer_path).expect("Failed to read test file.");
-    let mut cpu = Cpu::new();
-    cpu.load_instructions(&buffer);
-    cpu.execute().unwrap();
-    cpu
+    cpu.load_instructions(&buffer)
+        .expect("The test program should fit in memory.");
+}
+
+/// An I/O bus whose ports read as their low byte plus one, and which records
+/// every write.
+struct RecordingIoBus {
+    writes: Rc<RefCell<Vec<(u16, u8)>>>,
+}
+
+impl IoBus for RecordingIoBus {
+    fn read_port(&mut self, port: u16) -> u8 {
+        (port as u8).wrapping_add(1)
+    }
+
+    fn write_port(&mut self, port: u16, val: u8) {
+        self.writes.borrow_mut().push((port, val));
+    }
 }
 
 #[test]
@@ -120,6 +161,14 @@ fn test_memory_movs() {
     assert_eq!(cpu.ip, 0x0030u16);
 }
 
+#[test]
+fn test_byte_loads() {
+    let cpu = execute_file("byte_loads");
+
+    assert_eq!(cpu.gen_regs.ax, 0x1234u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.cx, 0x0012u16.to_le_bytes());
+}
+
 #[test]
 fn test_memory_num_loop() {
     let cpu = execute_file("mem_num_loop");
@@ -145,6 +194,21 @@ fn test_add_loop() {
     assert_eq!(cpu.ip, 0x0021u16);
 }
 
+#[test]
+fn test_loop_zero_cx() {
+    let mut cpu = Cpu::new();
+    load_file("loop_zero_cx", &mut cpu);
+
+    // With CX = 0, LOOP wraps CX around and repeats 65536 times.
+    let mut steps = 0;
+    while cpu.step().stop.is_none() {
+        steps += 1;
+    }
+    assert_eq!(steps, 1 + 2 * 0x10000);
+    assert_eq!(cpu.gen_regs.cx, 0u16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x0006);
+}
+
 #[test]
 fn test_draw_rectangle() {
     let cpu = execute_file("draw_rectangle");
@@ -155,6 +219,650 @@ fn test_draw_rectangle() {
     assert_eq!(cpu.ip, 0x0026u16);
 }
 
+#[test]
+fn test_logical() {
+    let cpu = execute_file("logical");
+
+    assert_eq!(cpu.gen_regs.ax, 0x0000u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.bx, 0x1ff0u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.cx, 0x0000u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.dx, 0xff00u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.si, 0x1200u16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x002eu16);
+
+    assert!(!cpu.flags.carry);
+    assert!(!cpu.flags.overflow);
+    assert!(!cpu.flags.zero);
+    assert!(cpu.flags.sign);
+    assert!(cpu.flags.parity);
+}
+
+#[test]
+fn test_shifts() {
+    let cpu = execute_file("shifts");
+
+    assert_eq!(cpu.gen_regs.ax, 0x0002u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.bx, 0x000fu16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.dx, 0xf800u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.si, 0x0003u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.di, 0x8000u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.bp, 0x0001u16.to_le_bytes());
+    assert_eq!(cpu.mem[1000], 0x40);
+    assert_eq!(cpu.ip, 0x0029u16);
+
+    assert!(cpu.flags.carry);
+    assert!(cpu.flags.overflow);
+}
+
+#[test]
+fn test_mul_div() {
+    let cpu = execute_file("mul_div");
+
+    assert_eq!(cpu.gen_regs.cx, 0xea60u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.si, 0xfff1u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.di, 0x0001u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.bp, 0xfffdu16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.dx, 0xffffu16.to_le_bytes());
+
+    // The division by zero is dispatched to the type 0 interrupt handler.
+    assert_eq!(cpu.gen_regs.ax, 0x1234u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.sp, 0xfffau16.to_le_bytes());
+    assert_eq!(cpu.mem[0xfffa..0xfffc], 0x003du16.to_le_bytes());
+    assert_eq!(cpu.mem[0xfffc..0xfffe], 0x0000u16.to_le_bytes());
+    assert_eq!(cpu.mem[0xffff] & 0xf0, 0xf0);
+    assert_eq!(cpu.ip, 0x0041u16);
+}
+
+#[test]
+fn test_inc_dec_neg() {
+    let cpu = execute_file("inc_dec_neg");
+
+    assert_eq!(cpu.gen_regs.ax, 0x0001u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.bx, 0x0080u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.cx, 0x0000u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.dx, 0xfffbu16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.si, 0x0003u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.di, 0xff01u16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x0031u16);
+
+    assert!(cpu.flags.carry);
+    assert!(cpu.flags.aux_carry);
+    assert!(!cpu.flags.overflow);
+}
+
+#[test]
+fn test_call_ret() {
+    let cpu = execute_file("call_ret");
+
+    assert_eq!(cpu.gen_regs.ax, 0x0000u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.bx, 0x0016u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.cx, 0x0002u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.dx, 0x0001u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.si, 0x0001u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.sp, 0x1000u16.to_le_bytes());
+    assert_eq!(cpu.seg_regs.cs, 0x0000u16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x0044u16);
+}
+
+#[test]
+fn test_string_ops() {
+    let cpu = execute_file("string_ops");
+
+    assert_eq!(
+        cpu.mem[1000..1008],
+        [0xcd, 0xab, 0xcd, 0xab, 0xcd, 0xab, 0xcd, 0xab]
+    );
+    assert_eq!(
+        cpu.mem[2000..2008],
+        [0xcd, 0xab, 0xcd, 0xab, 0xcd, 0xab, 0xcd, 0xab]
+    );
+
+    // REPNE SCASB stops at the first match, at the second byte.
+    assert_eq!(cpu.gen_regs.bx, 0x0006u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.dx, 0x03eau16.to_le_bytes());
+
+    assert_eq!(cpu.gen_regs.ax, 0xababu16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.cx, 0x0000u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.si, 0x03f0u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.di, 0x07d8u16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x0033u16);
+
+    assert!(cpu.flags.zero);
+}
+
+#[test]
+fn test_segments() {
+    let cpu = execute_file("segments");
+
+    assert_eq!(cpu.seg_regs.ds, 0x0100u16.to_le_bytes());
+    assert_eq!(cpu.seg_regs.es, 0x0200u16.to_le_bytes());
+    assert_eq!(cpu.seg_regs.ss, 0x0300u16.to_le_bytes());
+
+    // Each segment starts at 16 times the value of its register.
+    assert_eq!(cpu.mem[0x1010..0x1012], 0x1111u16.to_le_bytes());
+    assert_eq!(cpu.mem[0x2010..0x2012], 0x2222u16.to_le_bytes());
+    assert_eq!(cpu.mem[0x3010..0x3012], 0x3333u16.to_le_bytes());
+    assert_eq!(cpu.mem[0x300e..0x3010], 0x3333u16.to_le_bytes());
+    assert_eq!(cpu.mem[0x2020..0x2022], 0x3333u16.to_le_bytes());
+
+    assert_eq!(cpu.gen_regs.bx, 0x1111u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.cx, 0x2222u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.dx, 0x3333u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.ax, 0x1111u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.sp, 0x000eu16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.si, 0x0012u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.di, 0x0022u16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x0042u16);
+}
+
+#[test]
+fn test_interrupts() {
+    let cpu = execute_file("interrupts");
+
+    // Each handler was called exactly once: INT 21h, INT 3 and the first INTO.
+    assert_eq!(cpu.gen_regs.bx, 0x0001u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.cx, 0x0001u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.dx, 0x0001u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.ax, 0x0081u16.to_le_bytes());
+
+    // The first INTO pushed the FLAGS word with OF, SF and AF set, CS and IP.
+    let flags = u16::from_le_bytes([cpu.mem[0x0ffe], cpu.mem[0x0fff]]);
+    assert_eq!(flags & 0xfad1, 0xf890);
+    assert_eq!(cpu.mem[0x0ffc..0x0ffe], 0x0000u16.to_le_bytes());
+    assert_eq!(cpu.mem[0x0ffa..0x0ffc], 0x002fu16.to_le_bytes());
+
+    assert_eq!(cpu.gen_regs.sp, 0x1000u16.to_le_bytes());
+    assert_eq!(cpu.seg_regs.cs, 0x0000u16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x003au16);
+
+    assert!(!cpu.flags.overflow);
+    assert!(cpu.flags.sign);
+}
+
+#[test]
+fn test_adc_sbb() {
+    let cpu = execute_file("adc_sbb");
+
+    // 32-bit arithmetic: 0x0001ffff + 1 and 0x00010000 - 1.
+    assert_eq!(cpu.gen_regs.dx, 0x0002u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.cx, 0x0000u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.si, 0xffffu16.to_le_bytes());
+
+    // Register - Register/Memory forms.
+    assert_eq!(cpu.mem[0x100..0x102], 0x0001u16.to_le_bytes());
+    assert_eq!(cpu.mem[0x102..0x104], 0x0004u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.bx, 0x0ff0u16.to_le_bytes());
+
+    // Immediate - Accumulator forms.
+    assert_eq!(cpu.gen_regs.ax, 0x00eeu16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x003du16);
+
+    assert!(cpu.flags.carry);
+    assert!(cpu.flags.aux_carry);
+    assert!(!cpu.flags.overflow);
+    assert!(!cpu.flags.zero);
+}
+
+#[test]
+fn test_data_transfer() {
+    let cpu = execute_file("data_transfer");
+
+    assert_eq!(cpu.mem[0x200..0x202], 0xcdabu16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.cx, 0x1111u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.bx, 0x0300u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.si, 0x0310u16.to_le_bytes());
+
+    // LES and LDS load the far pointer 0050:2222.
+    assert_eq!(cpu.gen_regs.di, 0x2222u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.dx, 0x2222u16.to_le_bytes());
+    assert_eq!(cpu.seg_regs.es, 0x0050u16.to_le_bytes());
+    assert_eq!(cpu.seg_regs.ds, 0x0050u16.to_le_bytes());
+
+    // XLAT loads the table entry into AL, and LAHF reads the flags set by SAHF
+    // along with the reserved bit 1.
+    assert_eq!(cpu.gen_regs.ax, 0xd742u16.to_le_bytes());
+
+    // PUSHF pushes the reserved bits 12 to 15 set.
+    assert_eq!(cpu.mem[0x0ffe..0x1000], 0xf0d7u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.sp, 0x0ffeu16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x0046u16);
+
+    // POPF loads 0x0801.
+    assert!(cpu.flags.overflow);
+    assert!(cpu.flags.carry);
+    assert!(!cpu.flags.zero);
+    assert!(!cpu.flags.sign);
+    assert!(!cpu.flags.aux_carry);
+    assert!(!cpu.flags.parity);
+}
+
+#[test]
+fn test_bcd_adjust() {
+    let cpu = execute_file("bcd_adjust");
+
+    // Packed BCD: 38 + 45 = 83, 83 + 29 = 112 and 42 - 17 = 25.
+    assert_eq!(cpu.gen_regs.bx, 0x1283u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.cx, 0x0025u16.to_le_bytes());
+
+    // Unpacked BCD: 9 + 8 = 17 and 23 - 5 = 18.
+    assert_eq!(cpu.gen_regs.dx, 0x0107u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.si, 0x0108u16.to_le_bytes());
+
+    // AAM splits 63 in base 10, and in base 16 after AAD joins it back.
+    assert_eq!(cpu.gen_regs.di, 0x0603u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.bp, 0x030fu16.to_le_bytes());
+
+    // AAM with a zero base is dispatched to the type 0 interrupt handler.
+    assert_eq!(cpu.gen_regs.ax, 0x1234u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.sp, 0x1000u16.to_le_bytes());
+    assert_eq!(cpu.mem[0x0ffa..0x0ffc], 0x0040u16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x0046u16);
+}
+
+#[test]
+fn test_processor_control() {
+    let cpu = execute_file("processor_control");
+
+    // PUSHF saved the carry, direction and interrupt-enable flags while set.
+    let flags = u16::from_le_bytes([cpu.mem[0x0ffe], cpu.mem[0x0fff]]);
+    assert_eq!(flags & 0x0701, 0x0601);
+    assert!(!cpu.flags.carry);
+    assert!(!cpu.flags.direction);
+    assert!(!cpu.flags.interrupt_enable);
+
+    assert_eq!(cpu.gen_regs.bx, 0xff80u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.cx, 0x007fu16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.dx, 0x0000u16.to_le_bytes());
+
+    // The locked XCHG swapped the accumulator with memory.
+    assert_eq!(cpu.gen_regs.ax, 0x0000u16.to_le_bytes());
+    assert_eq!(cpu.mem[0x100..0x102], 0x007fu16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x0020u16);
+}
+
+#[test]
+fn test_port_io() {
+    let writes = Rc::new(RefCell::new(Vec::new()));
+    let io = RecordingIoBus {
+        writes: Rc::clone(&writes),
+    };
+    let cpu = execute_file_on("port_io", Cpu::with_io_bus(Box::new(io)));
+
+    assert_eq!(cpu.gen_regs.bx, 0x0061u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.ax, 0xfaf9u16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.dx, 0x03f8u16.to_le_bytes());
+    assert_eq!(cpu.ip, 0x000bu16);
+
+    // Word transfers access the port and the one following it.
+    assert_eq!(
+        *writes.borrow(),
+        [(0x43, 0xf9), (0x3f8, 0xf9), (0x3f9, 0xfa)]
+    );
+}
+
+#[test]
+fn test_port_io_default_bus() {
+    let cpu = execute_file("port_io");
+
+    assert_eq!(cpu.gen_regs.bx, 0x00ffu16.to_le_bytes());
+    assert_eq!(cpu.gen_regs.ax, 0xffffu16.to_le_bytes());
+}
+
+#[test]
+fn test_execution_error() {
+    let (cpu, err) = execute_file_err("execution_error");
+
+    assert_eq!(err.kind, ExecutionErrorKind::FarTarget);
+    assert_eq!((err.cs, err.ip), (0x0000, 0x0004));
+    assert_eq!(err.instruction.as_deref(), Some("jmp far bx"));
+    assert_eq!(err.registers.cx, 0x0004);
+    assert_eq!(err.registers.ip, 0x0006);
+    assert_eq!(
+        err.to_string(),
+        "far jump or call target is not in memory at 0000:0004 (`jmp far bx`)"
+    );
+    assert_eq!(
+        cpu.history().collect::<Vec<_>>(),
+        [(0x0000, 0x0000), (0x0000, 0x0003), (0x0000, 0x0004)]
+    );
+}
+
+#[test]
+fn test_execution_decode_error() {
+    let (cpu, err) = execute_file_err("decode_error");
+
+    let ExecutionErrorKind::Decode(decode_err) = &err.kind else {
+        panic!("Expected a decode error, got {:?}", err.kind);
+    };
+    assert_eq!(
+        decode_err.kind,
+        DecodeErrorKind::InvalidField {
+            field: Field::OpCode,
+            value: 0x60
+        }
+    );
+    assert_eq!((err.cs, err.ip), (0x0000, 0x0004));
+    assert_eq!(err.instruction, None);
+    assert_eq!(err.registers.cx, 0x0004);
+    assert_eq!(cpu.history().last(), Some((0x0000, 0x0004)));
+    assert_eq!(
+        err.to_string(),
+        "unknown OPCODE 0b01100000 at 0000:0004 (bytes: 60)"
+    );
+}
+
+#[test]
+fn test_step() {
+    let mut cpu = Cpu::new();
+    load_file("execution_error", &mut cpu);
+
+    let step = cpu.step();
+    assert_eq!((step.cs, step.ip, step.size), (0x0000, 0x0000, 3));
+    assert_eq!(step.instruction.unwrap().to_string(), "mov cx, 3");
+    assert_eq!(step.stop, None);
+    assert_eq!(cpu.ip, 0x0003);
+
+    let step = cpu.step();
+    assert_eq!((step.ip, step.size), (0x0003, 1));
+    assert_eq!(step.stop, None);
+    assert_eq!(cpu.gen_regs.cx, 4u16.to_le_bytes());
+
+    let step = cpu.step();
+    assert_eq!((step.ip, step.size), (0x0004, 2));
+    let Some(StopReason::Error(err)) = step.stop else {
+        panic!("Expected an error, got {:?}", step.stop);
+    };
+    assert_eq!(err.kind, ExecutionErrorKind::FarTarget);
+    assert_eq!(err.registers.ip, 0x0006);
+
+    // The failed instruction is rolled back and not recorded in the undo log.
+    assert_eq!(cpu.ip, 0x0004);
+    assert_eq!(cpu.step_back(), Some((0x0000, 0x0003)));
+    assert_eq!(cpu.gen_regs.cx, 3u16.to_le_bytes());
+}
+
+#[test]
+fn test_step_stop_reasons() {
+    let mut cpu = Cpu::new();
+    load_file("interrupts", &mut cpu);
+
+    let mut interrupts = Vec::new();
+    let halt = loop {
+        let step = cpu.step();
+        match step.stop {
+            Some(StopReason::Interrupt(vector)) => interrupts.push(vector),
+            Some(stop) => break (step.ip, stop),
+            None => {}
+        }
+    };
+    assert_eq!(interrupts, [0x21, 3, 4]);
+    assert_eq!(halt, (0x003a, StopReason::Halt));
+
+    // Stepping on a halt instruction halts again without moving.
+    let step = cpu.step();
+    assert_eq!((step.ip, step.stop), (0x003a, Some(StopReason::Halt)));
+    assert_eq!(cpu.ip, 0x003a);
+    // A division by zero raises a type 0 interrupt.
+    let mut cpu = Cpu::new();
+    load_file("mul_div", &mut cpu);
+    let step = loop {
+        let step = cpu.step();
+        if step.stop.is_some() {
+            break step;
+        }
+    };
+    assert_eq!(step.instruction.unwrap().to_string(), "div bx");
+    assert_eq!(step.stop, Some(StopReason::Interrupt(0)));
+}
+
+#[test]
+fn test_trace() {
+    let mut cpu = Cpu::new();
+    load_file("call_ret", &mut cpu);
+
+    let mut lines = Vec::new();
+    let mut before = cpu.registers();
+    for _ in 0..4 {
+        let step = cpu.step();
+        let after = cpu.registers();
+        lines.push(Trace::new(&step, &before, &after).to_string());
+        before = after;
+    }
+    assert_eq!(
+        lines,
+        [
+            "0000:0000  mov sp, 4096 ; sp:0x0->0x1000 ip:0x0->0x3 flags:->P",
+            "0000:0003  call $+54+0 ; sp:0x1000->0xffe ip:0x3->0x39 word [0xffe]:0x0->0x6",
+            "0000:0039  inc cx ; cx:0x0->0x1 ip:0x39->0x3a flags:P->",
+            "0000:003a  ret ; sp:0xffe->0x1000 ip:0x3a->0x6",
+        ]
+    );
+}
+
+#[test]
+fn test_breakpoints() {
+    let mut cpu = Cpu::new();
+    load_file("call_ret", &mut cpu);
+    cpu.add_breakpoint(0x0000, 0x0039);
+
+    // The procedure at 0x39 is called twice.
+    for caller in [0x0003, 0x0006] {
+        let step = cpu.run();
+        assert_eq!(step.stop, Some(StopReason::Breakpoint));
+        assert_eq!(step.ip, caller);
+        assert_eq!(cpu.ip, 0x0039);
+    }
+
+    assert!(cpu.remove_breakpoint(0x0000, 0x0039));
+    assert!(!cpu.remove_breakpoint(0x0000, 0x0039));
+    assert_eq!(cpu.run().stop, Some(StopReason::Halt));
+    assert_eq!(cpu.gen_regs.cx, 0x0002u16.to_le_bytes());
+}
+
+#[test]
+fn test_watchpoints() {
+    let mut cpu = Cpu::new();
+    load_file("call_ret", &mut cpu);
+
+    cpu.add_watchpoint(Watchpoint::Write(0x0fff..0x1000));
+    let step = cpu.run();
+    assert_eq!(step.ip, 0x0003);
+    assert_eq!(
+        step.stop,
+        Some(StopReason::Watchpoint(WatchpointHit::Write(MemoryWrite {
+            address: 0x0ffe,
+            width: Width::Word,
+            old: 0x0000,
+            new: 0x0006,
+        })))
+    );
+    assert!(cpu.remove_watchpoint(&Watchpoint::Write(0x0fff..0x1000)));
+
+    cpu.add_watchpoint(Watchpoint::Register(Register::CL));
+    let step = cpu.run();
+    assert_eq!(step.ip, 0x0039);
+    assert_eq!(
+        step.stop,
+        Some(StopReason::Watchpoint(WatchpointHit::Register {
+            register: Register::CL,
+            old: 0x00,
+            new: 0x01,
+        }))
+    );
+    cpu.remove_watchpoint(&Watchpoint::Register(Register::CL));
+
+    // The near call through memory reads its target at 1000.
+    cpu.add_watchpoint(Watchpoint::Read(0x03e8..0x03e9));
+    let step = cpu.run();
+    assert_eq!(step.ip, 0x001d);
+    assert_eq!(
+        step.stop,
+        Some(StopReason::Watchpoint(WatchpointHit::Read {
+            address: 0x03e8,
+            width: Width::Word,
+        }))
+    );
+    assert_eq!(cpu.watchpoints(), [Watchpoint::Read(0x03e8..0x03e9)]);
+}
+
+#[test]
+fn test_byte_read_watchpoint() {
+    let mut cpu = Cpu::new();
+    load_file("byte_loads", &mut cpu);
+
+    // The byte read of `mov al, [bx]` at 1000 is next to the watched byte at 1001,
+    // which is first read by `mov ah, [bx + 1]`.
+    cpu.add_watchpoint(Watchpoint::Read(0x03e9..0x03ea));
+    let step = cpu.run();
+    assert_eq!(step.ip, 0x0009);
+    assert_eq!(
+        step.stop,
+        Some(StopReason::Watchpoint(WatchpointHit::Read {
+            address: 0x03e9,
+            width: Width::Byte,
+        }))
+    );
+}
+
+#[test]
+fn test_snapshot() {
+    let mut cpu = Cpu::new();
+    load_file("call_ret", &mut cpu);
+    for _ in 0..10 {
+        cpu.step();
+    }
+    let mut snapshot = Vec::new();
+    cpu.save_snapshot(&mut snapshot).unwrap();
+    assert_eq!(snapshot[..10], *b"SIM8086\0\x01\x00");
+
+    let mut resumed = Cpu::new();
+    resumed.load_snapshot(snapshot.as_slice()).unwrap();
+    assert_eq!(resumed.registers(), cpu.registers());
+    assert_eq!(resumed.mem, cpu.mem);
+
+    cpu.execute().unwrap();
+    resumed.execute().unwrap();
+    assert_eq!(resumed.registers(), cpu.registers());
+    assert_eq!(resumed.mem, cpu.mem);
+}
+
+#[test]
+fn test_snapshot_errors() {
+    let mut cpu = Cpu::new();
+    let mut snapshot = Vec::new();
+    cpu.save_snapshot(&mut snapshot).unwrap();
+
+    let mut bad_magic = snapshot.clone();
+    bad_magic[0] = b'X';
+    let err = cpu.load_snapshot(bad_magic.as_slice()).unwrap_err();
+    assert!(matches!(err, SnapshotError::NotASnapshot));
+
+    let mut bad_version = snapshot.clone();
+    bad_version[8] = 2;
+    let err = cpu.load_snapshot(bad_version.as_slice()).unwrap_err();
+    assert!(matches!(err, SnapshotError::UnsupportedVersion(2)));
+
+    // The CPU is left unchanged by a truncated snapshot.
+    cpu.gen_regs.ax = 0x1234u16.to_le_bytes();
+    let err = cpu.load_snapshot(&snapshot[..100]).unwrap_err();
+    assert!(matches!(err, SnapshotError::Io(_)));
+    assert_eq!(cpu.gen_regs.ax, 0x1234u16.to_le_bytes());
+}
+
+#[test]
+fn test_step_back() {
+    let mut cpu = Cpu::new();
+    load_file("call_ret", &mut cpu);
+    let initial = (cpu.registers(), cpu.mem.clone());
+
+    let mut states = Vec::new();
+    loop {
+        let state = (cpu.registers(), cpu.mem.clone());
+        let step = cpu.step();
+        if step.stop == Some(StopReason::Halt) {
+            break;
+        }
+        states.push((step.cs, step.ip, state));
+    }
+
+    // Every instruction is undone in reverse order, back to the initial state.
+    while let Some((cs, ip, (registers, mem))) = states.pop() {
+        assert_eq!(cpu.step_back(), Some((cs, ip)));
+        assert_eq!(cpu.registers(), registers);
+        assert_eq!(cpu.mem, mem);
+    }
+    assert_eq!(cpu.step_back(), None);
+    assert_eq!((cpu.registers(), cpu.mem.clone()), initial);
+}
+
+#[test]
+fn test_run_back() {
+    let mut cpu = Cpu::new();
+    load_file("call_ret", &mut cpu);
+    cpu.execute().unwrap();
+
+    // The last write to the top of the stack is the CS pushed by the far call.
+    cpu.add_watchpoint(Watchpoint::Write(0x0ffe..0x1000));
+    let Some(StopReason::Watchpoint(WatchpointHit::Write(write))) = cpu.run_back() else {
+        panic!("Expected a write watchpoint");
+    };
+    assert_eq!(write.address, 0x0ffe);
+    assert_eq!(cpu.ip, 0x0021);
+    cpu.remove_watchpoint(&Watchpoint::Write(0x0ffe..0x1000));
+
+    cpu.add_watchpoint(Watchpoint::Register(Register::CX));
+    let Some(StopReason::Watchpoint(hit)) = cpu.run_back() else {
+        panic!("Expected a register watchpoint");
+    };
+    assert_eq!(
+        hit,
+        WatchpointHit::Register {
+            register: Register::CX,
+            old: 0x0001,
+            new: 0x0002,
+        }
+    );
+    assert_eq!(cpu.ip, 0x0039);
+    cpu.remove_watchpoint(&Watchpoint::Register(Register::CX));
+
+    cpu.add_breakpoint(0x0000, 0x0039);
+    assert_eq!(cpu.run_back(), Some(StopReason::Breakpoint));
+    assert_eq!(cpu.ip, 0x0039);
+    assert_eq!(cpu.gen_regs.cx, 0x0000u16.to_le_bytes());
+
+    assert_eq!(cpu.run_back(), None);
+    assert_eq!(cpu.ip, 0x0000);
+}
+
+#[test]
+fn test_undo_limit() {
+    let mut cpu = execute_file("draw_rectangle");
+
+    let mut undone = 0;
+    while cpu.step_back().is_some() {
+        undone += 1;
+    }
+    assert_eq!(undone, UNDO_LEN);
+}
+
+#[test]
+fn test_load_oversized_program() {
+    let mut cpu = Cpu::new();
+    let err = cpu.load_instructions(&[0x90; 0x10000]).unwrap_err();
+    assert_eq!(
+        err,
+        ProgramSizeError {
+            size: 0x10000,
+            capacity: 0xffff,
+        }
+    );
+    assert!(cpu.mem.iter().all(|&byte| byte == 0));
+
+    cpu.load_instructions(&[0x90; 0xffff]).unwrap();
+    assert_eq!(cpu.mem[0xfffe], 0x90);
+    assert_eq!(cpu.mem[0xffff], 0xf4);
+}
+
 // #[test]
 // fn test_draw_rectangle_2() {
 //     let cpu = execute_file("draw_rectangle_2");
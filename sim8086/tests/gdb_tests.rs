@@ -0,0 +1,136 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// A minimal GDB Remote Serial Protocol client.
+struct Client {
+    stream: TcpStream,
+}
+
+impl Client {
+    /// Sends a packet and returns the payload of the reply.
+    fn request(&mut self, payload: &str) -> String {
+        let checksum = payload.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        write!(self.stream, "${payload}#{checksum:02x}").unwrap();
+
+        let mut byte = [0u8; 1];
+        self.stream.read_exact(&mut byte).unwrap();
+        assert_eq!(byte[0], b'+', "Packet `{payload}` was not acknowledged.");
+        self.reply()
+    }
+
+    /// Rejects the last reply and returns the payload of the reply sent again.
+    fn reject(&mut self) -> String {
+        self.stream.write_all(b"-").unwrap();
+        self.reply()
+    }
+
+    /// Reads a reply, acknowledging it, and returns its payload.
+    fn reply(&mut self) -> String {
+        let mut byte = [0u8; 1];
+        self.stream.read_exact(&mut byte).unwrap();
+        assert_eq!(byte[0], b'$');
+        let mut reply = Vec::new();
+        loop {
+            self.stream.read_exact(&mut byte).unwrap();
+            if byte[0] == b'#' {
+                break;
+            }
+            reply.push(byte[0]);
+        }
+        let mut checksum = [0u8; 2];
+        self.stream.read_exact(&mut checksum).unwrap();
+        let expected = reply.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+        assert_eq!(checksum, format!("{expected:02x}").as_bytes());
+        self.stream.write_all(b"+").unwrap();
+
+        String::from_utf8(reply).unwrap()
+    }
+}
+
+#[test]
+fn test_gdbserver() {
+    let program = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/execute/call_ret");
+    let mut server = Command::new(env!("CARGO_BIN_EXE_sim8086"))
+        .arg("gdbserver")
+        .arg("--port")
+        .arg("0")
+        .arg(program)
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run `sim8086 gdbserver`.");
+
+    let mut line = String::new();
+    BufReader::new(server.stderr.take().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let addr = line.trim().strip_prefix("Listening on ").unwrap();
+    let stream = TcpStream::connect(addr).unwrap();
+    stream.set_nodelay(true).unwrap();
+    let mut client = Client { stream };
+
+    assert_eq!(
+        client.request("qSupported:swbreak+;hwbreak+"),
+        "PacketSize=4000;swbreak+"
+    );
+    assert_eq!(client.request("?"), "S05");
+    assert_eq!(client.request("vMustReplyEmpty"), "");
+
+    // ax, cx, dx, bx, sp, bp, si, di, ip, flags, cs, ss, ds, es, fs, gs.
+    let registers = client.request("g");
+    assert_eq!(registers.len(), 16 * 8);
+    assert_eq!(&registers[64..80], "0000000002f00000");
+
+    // mov sp, 4096
+    assert_eq!(client.request("s"), "S05");
+    assert_eq!(client.request("p8"), "03000000");
+    assert_eq!(client.reject(), "03000000");
+    assert_eq!(client.request("p4"), "00100000");
+
+    // mov ax, -26215, then back to the first call.
+    assert_eq!(client.request("sb"), "S05");
+    assert_eq!(client.request("p0"), "99990000");
+    assert_eq!(client.request("p8"), "0e000000");
+    assert_eq!(client.request("s10000"), "E01");
+    assert_eq!(client.request("c10000"), "E01");
+    assert_eq!(client.request("p8"), "0e000000");
+
+    // The first call into the procedure at 0x39.
+    assert_eq!(client.request("Z0,39,1"), "OK");
+    assert_eq!(client.request("c3"), "T05swbreak:;");
+    assert_eq!(client.request("p8"), "39000000");
+    assert_eq!(client.request("mffe,2"), "0600");
+    assert_eq!(client.request("z0,39,1"), "OK");
+
+    assert_eq!(client.request("P1=34120000"), "OK");
+    assert_eq!(client.request("p1"), "34120000");
+    assert_eq!(client.request("M3e8,2:aabb"), "OK");
+    assert_eq!(client.request("m3e8,2"), "aabb");
+
+    // Memory beyond 1 MiB, or too much of it at once.
+    assert_eq!(client.request("m0,ffffffffffff"), "E01");
+    assert_eq!(client.request("m100000,1"), "E01");
+    assert_eq!(client.request("mfffff,2"), "E01");
+    assert_eq!(client.request("mffffffffffffffff,2"), "E01");
+    assert_eq!(client.request("Mffffffffffffffff,1:00"), "E01");
+    assert_eq!(client.request("Z2,ffffffffffffffff,2"), "E01");
+    assert_eq!(client.request("mfffff,1"), "00");
+
+    // mov [1000], word 59
+    assert_eq!(client.request("Z2,3e8,2"), "OK");
+    assert_eq!(client.request("c"), "T05watch:3e8;");
+    assert_eq!(client.request("m3e8,2"), "3b00");
+    assert_eq!(client.request("z2,3e8,2"), "OK");
+
+    // call word [1000]
+    assert_eq!(client.request("Z3,3e8,2"), "OK");
+    assert_eq!(client.request("c"), "T05rwatch:3e8;");
+    assert_eq!(client.request("p8"), "3b000000");
+    assert_eq!(client.request("z3,3e8,2"), "OK");
+
+    assert_eq!(client.request("c"), "W00");
+    assert_eq!(client.request("D"), "OK");
+
+    assert!(server.wait().unwrap().success());
+}